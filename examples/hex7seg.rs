@@ -1,31 +1,13 @@
 use std::io;
 
-fn main() {
+fn main() -> Result<(), plaza::Error> {
     let mut chip = plaza::dev::GAL16V8::new();
 
-    let rin = chip.input(2, "rin");
+    let rin = chip.input(2, "rin")?;
 
-    let bit3 = chip.input(3, "bit3");
-    let bit2 = chip.input(4, "bit2");
-    let bit1 = chip.input(5, "bit1");
-    let bit0 = chip.input(6, "bit0");
+    let bits = chip.input_bus(&[6, 5, 4, 3], "bit")?;
 
-    let mut d0 = !&bit3 & !&bit2 & !&bit1 & !&bit0;
-    let     d1 = !&bit3 & !&bit2 & !&bit1 &  &bit0;
-    let     d2 = !&bit3 & !&bit2 &  &bit1 & !&bit0;
-    let     d3 = !&bit3 & !&bit2 &  &bit1 &  &bit0;
-    let     d4 = !&bit3 &  &bit2 & !&bit1 & !&bit0;
-    let     d5 = !&bit3 &  &bit2 & !&bit1 &  &bit0;
-    let     d6 = !&bit3 &  &bit2 &  &bit1 & !&bit0;
-    let     d7 = !&bit3 &  &bit2 &  &bit1 &  &bit0;
-    let     d8 =  &bit3 & !&bit2 & !&bit1 & !&bit0;
-    let     d9 =  &bit3 & !&bit2 & !&bit1 &  &bit0;
-    let     da =  &bit3 & !&bit2 &  &bit1 & !&bit0;
-    let     db =  &bit3 & !&bit2 &  &bit1 &  &bit0;
-    let     dc =  &bit3 &  &bit2 & !&bit1 & !&bit0;
-    let     dd =  &bit3 &  &bit2 & !&bit1 &  &bit0;
-    let     de =  &bit3 &  &bit2 &  &bit1 & !&bit0;
-    let     df =  &bit3 &  &bit2 &  &bit1 &  &bit0;
+    let mut d = plaza::decoder(&bits);
 
     // The rules for rin and rout:
     //   Least significant digit should have rin set high
@@ -33,9 +15,9 @@ fn main() {
     //   Remaining digits should have rin set to rout from their more-significant neighbor
     // N.B. if you don't ever want leading blanks, you can also just set all rin inputs high
 
-    let rout = &rin | !&d0;
+    let rout = &rin | !&d[0x0];
 
-    d0 &= &rin;
+    d[0x0] &= &rin;
 
     //    #A#               #A#      #A#
     //   #   #        #        #        #
@@ -77,22 +59,26 @@ fn main() {
     //   #        #   #    #        #
     //    #D#      #D#      #D#
 
-    let sega = &d0       | &d2 | &d3       | &d5 | &d6 | &d7 | &d8 | &d9 | &da                   | &de | &df ;
-    let segb = &d0 | &d1 | &d2 | &d3 | &d4             | &d7 | &d8 | &d9 | &da             | &dd             ;
-    let segc = &d0 | &d1       | &d3 | &d4 | &d5 | &d6 | &d7 | &d8 | &d9 | &da | &db       | &dd             ;
-    let segd = &d0       | &d2 | &d3       | &d5 | &d6       | &d8             | &db | &dc | &dd | &de       ;
-    let sege = &d0       | &d2                   | &d6       | &d8       | &da | &db | &dc | &dd | &de | &df ;
-    let segf = &d0                   | &d4 | &d5 | &d6       | &d8 | &d9 | &da | &db             | &de | &df ;
-    let segg =             &d2 | &d3 | &d4 | &d5 | &d6       | &d8 | &d9 | &da | &db | &dc | &dd | &de | &df ;
+    let sega = &d[0x0]           | &d[0x2] | &d[0x3]           | &d[0x5] | &d[0x6] | &d[0x7] | &d[0x8] | &d[0x9] | &d[0xa]                               | &d[0xe] | &d[0xf] ;
+    let segb = &d[0x0] | &d[0x1] | &d[0x2] | &d[0x3] | &d[0x4]                     | &d[0x7] | &d[0x8] | &d[0x9] | &d[0xa]                     | &d[0xd]                     ;
+    let segc = &d[0x0] | &d[0x1]           | &d[0x3] | &d[0x4] | &d[0x5] | &d[0x6] | &d[0x7] | &d[0x8] | &d[0x9] | &d[0xa] | &d[0xb]           | &d[0xd]                     ;
+    let segd = &d[0x0]           | &d[0x2] | &d[0x3]           | &d[0x5] | &d[0x6]           | &d[0x8]                     | &d[0xb] | &d[0xc] | &d[0xd] | &d[0xe]           ;
+    let sege = &d[0x0]           | &d[0x2]                               | &d[0x6]           | &d[0x8]           | &d[0xa] | &d[0xb] | &d[0xc] | &d[0xd] | &d[0xe] | &d[0xf] ;
+    let segf = &d[0x0]                               | &d[0x4] | &d[0x5] | &d[0x6]           | &d[0x8] | &d[0x9] | &d[0xa] | &d[0xb]                     | &d[0xe] | &d[0xf] ;
+    let segg =                     &d[0x2] | &d[0x3] | &d[0x4] | &d[0x5] | &d[0x6]           | &d[0x8] | &d[0x9] | &d[0xa] | &d[0xb] | &d[0xc] | &d[0xd] | &d[0xe] | &d[0xf] ;
 
-    chip.combinatorial_output(12, rout);
-    chip.combinatorial_output(13, sega);
-    chip.combinatorial_output(14, segb);
-    chip.combinatorial_output(15, segc);
-    chip.combinatorial_output(16, segd);
-    chip.combinatorial_output(17, sege);
-    chip.combinatorial_output(18, segf);
-    chip.combinatorial_output(19, segg);
+    chip.combinatorial_output(12, rout)?;
+    chip.combinatorial_output(13, sega)?;
+    chip.combinatorial_output(14, segb)?;
+    chip.combinatorial_output(15, segc)?;
+    chip.combinatorial_output(16, segd)?;
+    chip.combinatorial_output(17, sege)?;
+    chip.combinatorial_output(18, segf)?;
+    chip.combinatorial_output(19, segg)?;
 
-    chip.write(&mut io::stdout()).unwrap();
+    for lint in chip.write(&mut io::stdout())? {
+        eprintln!("{}", lint);
+    }
+
+    Ok(())
 }
@@ -0,0 +1,103 @@
+use crate::bus::*;
+use crate::truthtable::*;
+
+// Select values with no corresponding input give 0.
+pub fn mux(select: &Bus, inputs: &[TruthTable]) -> TruthTable {
+    let mut tt = TruthTable::never();
+
+    for (i, input) in inputs.iter().enumerate() {
+        tt |= select.eq_const(i as u64) & input;
+    }
+
+    tt
+}
+
+// One output per select value, true exactly when select has that value.
+pub fn decoder(select: &Bus) -> Vec<TruthTable> {
+    (0 .. 1 << select.width()).map(|i| select.eq_const(i)).collect()
+}
+
+// The highest numbered true input wins. The second result is true when any input is, which tells
+// input 0 apart from no input at all, since both encode as 0.
+pub fn priority_encoder(inputs: &[TruthTable]) -> (Bus, TruthTable) {
+    let mut width = 0;
+    while 1 << width < inputs.len() {
+        width += 1;
+    }
+
+    let mut bits = vec![TruthTable::never(); width];
+    let mut higher = TruthTable::never();
+
+    for (i, input) in inputs.iter().enumerate().rev() {
+        let wins = input & !&higher;
+        for (j, bit) in bits.iter_mut().enumerate() {
+            if i & (1 << j) != 0 {
+                *bit |= &wins;
+            }
+        }
+        higher |= input;
+    }
+
+    (Bus::new("priority", bits), higher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::var::Var;
+    use std::collections::BTreeMap;
+
+    type Row = BTreeMap<Var, bool>;
+
+    fn assign(row: &mut Row, name: &str, width: usize, value: u64) {
+        for i in 0 .. width {
+            row.insert(Var::from(&*format!("{}[{}]", name, i)), value & (1 << i) != 0);
+        }
+    }
+
+    fn value(bus: &Bus, row: &Row) -> u64 {
+        bus.bits.iter().enumerate().fold(0, |x, (i, b)| x | (b.eval(row).unwrap() as u64) << i)
+    }
+
+    #[test]
+    fn mux_exhaustive() {
+        let (select, data) = (Bus::vars("s", 2), Bus::vars("d", 3));
+        let tt = mux(&select, &data.bits);
+        for s in 0 .. 4 {
+            for d in 0 .. 8 {
+                let mut row = Row::new();
+                assign(&mut row, "s", select.width(), s);
+                assign(&mut row, "d", data.width(), d);
+                assert_eq!(tt.eval(&row).unwrap(), s < 3 && d & (1 << s) != 0);
+            }
+        }
+    }
+
+    #[test]
+    fn decoder_exhaustive() {
+        let select = Bus::vars("s", 3);
+        let outputs = decoder(&select);
+        assert_eq!(outputs.len(), 8);
+        for s in 0 .. 8 {
+            let mut row = Row::new();
+            assign(&mut row, "s", select.width(), s);
+            for (i, out) in outputs.iter().enumerate() {
+                assert_eq!(out.eval(&row).unwrap(), i as u64 == s);
+            }
+        }
+    }
+
+    #[test]
+    fn priority_encoder_exhaustive() {
+        let inputs = Bus::vars("x", 5);
+        let (encoded, any) = priority_encoder(&inputs.bits);
+        assert_eq!(encoded.width(), 3);
+        for x in 0 .. 32u64 {
+            let mut row = Row::new();
+            assign(&mut row, "x", inputs.width(), x);
+            let highest = if x == 0 { 0 } else { 63 - x.leading_zeros() as u64 };
+            assert_eq!(value(&encoded, &row), highest);
+            assert_eq!(any.eval(&row).unwrap(), x != 0);
+        }
+    }
+}
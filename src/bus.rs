@@ -0,0 +1,327 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops;
+
+use crate::error::*;
+use crate::truthtable::*;
+
+// bits[0] is the least significant bit.
+#[derive(Clone, Debug)]
+pub struct Bus {
+    pub name: String,
+    pub bits: Vec<TruthTable>,
+}
+
+impl Bus {
+    pub fn new(name: &str, bits: Vec<TruthTable>) -> Bus {
+        Bus {
+            name: name.to_string(),
+            bits,
+        }
+    }
+
+    // A bus of fresh variables named name[0], name[1], ...
+    pub fn vars(name: &str, width: usize) -> Bus {
+        Bus::new(name, (0 .. width).map(|i| TruthTable::from(&*format!("{}[{}]", name, i))).collect())
+    }
+
+    pub fn constant(width: usize, value: u64) -> Bus {
+        let bits = (0 .. width).map(|i| if i < 64 && value & (1 << i) != 0 { TruthTable::always() } else { TruthTable::never() }).collect();
+        Bus::new(&format!("{}", value), bits)
+    }
+
+    pub fn width(&self) -> usize { self.bits.len() }
+
+    fn bit(&self, i: usize) -> TruthTable {
+        self.bits.get(i).cloned().unwrap_or_else(TruthTable::never)
+    }
+
+    fn zip(&self, that: &Bus, name: String, f: &dyn Fn(&TruthTable, &TruthTable) -> TruthTable) -> Bus {
+        let width = self.width().max(that.width());
+        Bus {
+            name,
+            bits: (0 .. width).map(|i| f(&self.bit(i), &that.bit(i))).collect(),
+        }
+    }
+
+    pub fn eq_const(&self, value: u64) -> TruthTable {
+        if self.width() < 64 && value >> self.width() != 0 {
+            return TruthTable::never();
+        }
+
+        let mut tt = TruthTable::always();
+        for (i, b) in self.bits.iter().enumerate() {
+            if i < 64 && value & (1 << i) != 0 {
+                tt &= b;
+            } else {
+                tt &= !b;
+            }
+        }
+        tt
+    }
+
+    pub fn equals(&self, that: &Bus) -> TruthTable {
+        let mut tt = TruthTable::always();
+        for i in 0 .. self.width().max(that.width()) {
+            tt &= !(self.bit(i) ^ that.bit(i));
+        }
+        tt
+    }
+
+    // Unsigned comparisons; buses of different widths are zero-extended.
+    pub fn lt(&self, that: &Bus) -> TruthTable {
+        let mut tt = TruthTable::never();
+        for i in 0 .. self.width().max(that.width()) {
+            let (a, b) = (self.bit(i), that.bit(i));
+            tt = (!&a & &b) | (!(&a ^ &b) & tt);
+        }
+        tt
+    }
+
+    pub fn le(&self, that: &Bus) -> TruthTable { !that.lt(self) }
+    pub fn gt(&self, that: &Bus) -> TruthTable { that.lt(self) }
+    pub fn ge(&self, that: &Bus) -> TruthTable { !self.lt(that) }
+
+    // Returns the sum and the carry out of the most significant bit.
+    pub fn add_carry(&self, that: &Bus, carry: &TruthTable) -> (Bus, TruthTable) {
+        let mut carry = carry.clone();
+        let mut bits = Vec::new();
+
+        for i in 0 .. self.width().max(that.width()) {
+            let (a, b) = (self.bit(i), that.bit(i));
+            let half = &a ^ &b;
+            bits.push(&half ^ &carry);
+            carry = (&a & &b) | (&carry & &half);
+        }
+
+        (Bus::new(&format!("({} + {})", self.name, that.name), bits), carry)
+    }
+
+    pub fn add(&self, that: &Bus) -> (Bus, TruthTable) {
+        self.add_carry(that, &TruthTable::never())
+    }
+
+    // Returns the difference and the borrow out of the most significant bit.
+    pub fn sub(&self, that: &Bus) -> (Bus, TruthTable) {
+        let width = self.width().max(that.width());
+        let inverted = Bus::new(&that.name, (0 .. width).map(|i| !that.bit(i)).collect());
+        let (mut diff, carry) = self.add_carry(&inverted, &TruthTable::always());
+        diff.name = format!("({} - {})", self.name, that.name);
+        (diff, !carry)
+    }
+
+    // Wraps around to zero on overflow.
+    pub fn increment(&self) -> Bus {
+        let mut carry = TruthTable::always();
+        let mut bits = Vec::new();
+
+        for b in &self.bits {
+            bits.push(b ^ &carry);
+            carry &= b;
+        }
+
+        Bus::new(&format!("({} + 1)", self.name), bits)
+    }
+}
+
+impl fmt::Display for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.bits.is_empty() {
+            write!(f, "{}[]", self.name)
+        } else {
+            write!(f, "{}[{}:0]", self.name, self.width() - 1)
+        }
+    }
+}
+
+impl ops::BitAnd for &Bus {
+    type Output = Bus;
+
+    fn bitand(self, x: &Bus) -> Bus { self.zip(x, format!("({} & {})", self.name, x.name), &|a, b| a & b) }
+}
+
+impl ops::BitAnd for Bus { type Output = Bus; fn bitand(self, x: Bus) -> Bus { &self & &x } }
+impl ops::BitAnd<&Bus> for Bus { type Output = Bus; fn bitand(self, x: &Bus) -> Bus { &self & x } }
+impl ops::BitAnd<Bus> for &Bus { type Output = Bus; fn bitand(self, x: Bus) -> Bus { self & &x } }
+
+impl ops::BitOr for &Bus {
+    type Output = Bus;
+
+    fn bitor(self, x: &Bus) -> Bus { self.zip(x, format!("({} | {})", self.name, x.name), &|a, b| a | b) }
+}
+
+impl ops::BitOr for Bus { type Output = Bus; fn bitor(self, x: Bus) -> Bus { &self | &x } }
+impl ops::BitOr<&Bus> for Bus { type Output = Bus; fn bitor(self, x: &Bus) -> Bus { &self | x } }
+impl ops::BitOr<Bus> for &Bus { type Output = Bus; fn bitor(self, x: Bus) -> Bus { self | &x } }
+
+impl ops::BitXor for &Bus {
+    type Output = Bus;
+
+    fn bitxor(self, x: &Bus) -> Bus { self.zip(x, format!("({} ^ {})", self.name, x.name), &|a, b| a ^ b) }
+}
+
+impl ops::BitXor for Bus { type Output = Bus; fn bitxor(self, x: Bus) -> Bus { &self ^ &x } }
+impl ops::BitXor<&Bus> for Bus { type Output = Bus; fn bitxor(self, x: &Bus) -> Bus { &self ^ x } }
+impl ops::BitXor<Bus> for &Bus { type Output = Bus; fn bitxor(self, x: Bus) -> Bus { self ^ &x } }
+
+impl ops::Not for &Bus {
+    type Output = Bus;
+
+    fn not(self) -> Bus {
+        Bus {
+            name: format!("/{}", self.name),
+            bits: self.bits.iter().map(|b| !b).collect(),
+        }
+    }
+}
+
+impl ops::Not for Bus { type Output = Bus; fn not(self) -> Bus { !&self } }
+
+// Shifts keep the width of the bus, filling with zeroes.
+impl ops::Shl<usize> for &Bus {
+    type Output = Bus;
+
+    fn shl(self, n: usize) -> Bus {
+        Bus {
+            name: format!("({} << {})", self.name, n),
+            bits: (0 .. self.width()).map(|i| if i >= n { self.bits[i - n].clone() } else { TruthTable::never() }).collect(),
+        }
+    }
+}
+
+impl ops::Shl<usize> for Bus { type Output = Bus; fn shl(self, n: usize) -> Bus { &self << n } }
+
+impl ops::Shr<usize> for &Bus {
+    type Output = Bus;
+
+    fn shr(self, n: usize) -> Bus {
+        Bus {
+            name: format!("({} >> {})", self.name, n),
+            bits: (0 .. self.width()).map(|i| self.bit(i + n)).collect(),
+        }
+    }
+}
+
+impl ops::Shr<usize> for Bus { type Output = Bus; fn shr(self, n: usize) -> Bus { &self >> n } }
+
+// A bus whose values are known by name. Codes with no name never occur, which the device uses as
+// don't-cares when minimizing.
+#[derive(Clone, Debug)]
+pub struct Symbolic {
+    pub bus: Bus,
+    pub codes: BTreeMap<String, u64>,
+}
+
+impl Symbolic {
+    pub fn new(bus: Bus, codes: &[(&str, u64)]) -> Result<Symbolic> {
+        let mut map = BTreeMap::new();
+
+        for (name, code) in codes {
+            if bus.width() < 64 && code >> bus.width() != 0 {
+                return Err(Error::SymbolCode{bus: bus.to_string(), symbol: name.to_string(), code: *code});
+            }
+            if map.insert(name.to_string(), *code).is_some() {
+                return Err(Error::DuplicateSymbol{bus: bus.to_string(), symbol: name.to_string()});
+            }
+        }
+
+        Ok(Symbolic {
+            bus,
+            codes: map,
+        })
+    }
+
+    pub fn is(&self, name: &str) -> Result<TruthTable> {
+        match self.codes.get(name) {
+            Some(code) => Ok(self.bus.eq_const(*code)),
+            None => Err(Error::UnknownSymbol{bus: self.bus.to_string(), symbol: name.to_string()}),
+        }
+    }
+
+    pub fn valid(&self) -> TruthTable {
+        self.codes.values().fold(TruthTable::never(), |tt, code| tt | self.bus.eq_const(*code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::var::Var;
+
+    type Row = BTreeMap<Var, bool>;
+
+    const WIDTH: usize = 3;
+
+    fn assignment(a: u64, b: u64) -> Row {
+        let mut map = Row::new();
+        for i in 0 .. WIDTH {
+            map.insert(Var::from(&*format!("a[{}]", i)), a & (1 << i) != 0);
+            map.insert(Var::from(&*format!("b[{}]", i)), b & (1 << i) != 0);
+        }
+        map
+    }
+
+    fn value(bus: &Bus, row: &Row) -> u64 {
+        bus.bits.iter().enumerate().fold(0, |x, (i, b)| x | (b.eval(row).unwrap() as u64) << i)
+    }
+
+    fn for_all(check: &dyn Fn(u64, u64, &Row)) {
+        for a in 0 .. 1 << WIDTH {
+            for b in 0 .. 1 << WIDTH {
+                check(a, b, &assignment(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let (a, b) = (Bus::vars("a", WIDTH), Bus::vars("b", WIDTH));
+        let (sum, carry) = a.add(&b);
+        let (diff, borrow) = a.sub(&b);
+        let inc = a.increment();
+        let mask = (1 << WIDTH) - 1;
+
+        for_all(&|x, y, row| {
+            assert_eq!(value(&sum, row), (x + y) & mask);
+            assert_eq!(carry.eval(row).unwrap(), x + y > mask);
+            assert_eq!(value(&diff, row), x.wrapping_sub(y) & mask);
+            assert_eq!(borrow.eval(row).unwrap(), x < y);
+            assert_eq!(value(&inc, row), (x + 1) & mask);
+        });
+    }
+
+    #[test]
+    fn comparisons() {
+        let (a, b) = (Bus::vars("a", WIDTH), Bus::vars("b", WIDTH));
+        let (lt, le, gt, ge, eq) = (a.lt(&b), a.le(&b), a.gt(&b), a.ge(&b), a.equals(&b));
+        let five = a.eq_const(5);
+
+        for_all(&|x, y, row| {
+            assert_eq!(lt.eval(row).unwrap(), x < y);
+            assert_eq!(le.eval(row).unwrap(), x <= y);
+            assert_eq!(gt.eval(row).unwrap(), x > y);
+            assert_eq!(ge.eval(row).unwrap(), x >= y);
+            assert_eq!(eq.eval(row).unwrap(), x == y);
+            assert_eq!(five.eval(row).unwrap(), x == 5);
+        });
+
+        assert_eq!(a.eq_const(8), TruthTable::never());
+    }
+
+    #[test]
+    fn mixed_widths_and_shifts() {
+        let (a, b) = (Bus::vars("a", WIDTH), Bus::vars("b", WIDTH));
+        let narrow = Bus::new("b", b.bits[.. 2].to_vec());
+        let (sum, _) = a.add(&narrow);
+        let (shl, shr) = (&a << 1, &a >> 1);
+
+        for_all(&|x, y, row| {
+            assert_eq!(value(&sum, row), (x + (y & 3)) & 7);
+            assert_eq!(value(&shl, row), (x << 1) & 7);
+            assert_eq!(value(&shr, row), x >> 1);
+            assert_eq!(a.lt(&narrow).eval(row).unwrap(), x < (y & 3));
+        });
+
+        assert_eq!(Bus::constant(WIDTH, 6).eq_const(6), TruthTable::always());
+    }
+}
@@ -1,9 +1,14 @@
 use lazy_static::lazy_static;
-use std::collections::BTreeMap;
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::io;
 use std::num;
+use std::panic;
 
+use crate::bus::*;
+use crate::error::*;
+use crate::hazard::*;
 use crate::truthtable::*;
 use crate::var::*;
 
@@ -27,14 +32,54 @@ enum OutputMode {
     Disabled,
     Combinatorial {
         tt: TruthTable,
+        active_low: bool,
     },
 }
 
+// Positive and Negative refer to the polarity fuse, so they describe the pin rather than the
+// declared function when the output is active-low.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Polarity {
+    Auto,
+    PreferPositive,
+    PreferNegative,
+    Positive,
+    Negative,
+}
+
+impl fmt::Display for Polarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Polarity::Auto => write!(f, "automatic polarity"),
+            Polarity::PreferPositive => write!(f, "preferably positive polarity"),
+            Polarity::PreferNegative => write!(f, "preferably negative polarity"),
+            Polarity::Positive => write!(f, "positive polarity"),
+            Polarity::Negative => write!(f, "negative polarity"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MacrocellUsage {
+    pub pin: u32,
+    pub polarity: Polarity,
+    pub terms: usize,
+    pub available: usize,
+    pub literals: usize,
+}
+
+impl fmt::Display for MacrocellUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Pin {}: {}, {} of {} terms, {} literals", self.pin, self.polarity, self.terms, self.available, self.literals)
+    }
+}
+
 impl fmt::Display for OutputMode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             OutputMode::Disabled => write!(f, "disabled"),
-            OutputMode::Combinatorial {tt} => write!(f, "always-enabled combinatorial\n{}", tt),
+            OutputMode::Combinatorial {tt, active_low: false} => write!(f, "always-enabled combinatorial\n{}", tt),
+            OutputMode::Combinatorial {tt, active_low: true} => write!(f, "always-enabled active-low combinatorial\n{}", tt),
         }
     }
 }
@@ -75,72 +120,457 @@ lazy_static! {
     };
 }
 
+type Location = &'static panic::Location<'static>;
+
 pub struct GAL16V8 {
     inputs: BTreeMap<Var, u32>,
+    active_low_inputs: BTreeSet<Var>,
     outputs: BTreeMap<u32, OutputMode>,
+    polarities: BTreeMap<u32, Polarity>,
+    hazard_free: BTreeSet<u32>,
+    input_locations: BTreeMap<Var, Location>,
+    output_locations: BTreeMap<u32, Location>,
+    assumptions: Vec<(TruthTable, Location)>,
+    care: TruthTable,
+    fitted: OnceCell<(BTreeMap<u32, Product>, Vec<Diagnostic>)>,
+}
+
+impl Default for GAL16V8 {
+    fn default() -> GAL16V8 { GAL16V8::new() }
 }
 
 impl GAL16V8 {
     pub fn new() -> GAL16V8 {
         GAL16V8 {
             inputs: BTreeMap::new(),
+            active_low_inputs: BTreeSet::new(),
             outputs: BTreeMap::new(),
+            polarities: BTreeMap::new(),
+            hazard_free: BTreeSet::new(),
+            input_locations: BTreeMap::new(),
+            output_locations: BTreeMap::new(),
+            assumptions: Vec::new(),
+            care: TruthTable::always(),
+            fitted: OnceCell::new(),
         }
     }
 
-    pub fn input(&mut self, pin: u32, name: &str) -> TruthTable {
+    #[track_caller]
+    pub fn input(&mut self, pin: u32, name: &str) -> Result<TruthTable> {
+        self.declare_input(pin, name, false)
+    }
+
+    // The returned table is true when the pin is low, so logic can be written in asserted terms.
+    #[track_caller]
+    pub fn input_active_low(&mut self, pin: u32, name: &str) -> Result<TruthTable> {
+        self.declare_input(pin, name, true)
+    }
+
+    #[track_caller]
+    fn declare_input(&mut self, pin: u32, name: &str, active_low: bool) -> Result<TruthTable> {
         let v = Var::from(name);
-        if let Some(inum) = INPUTS.get(&pin) {
-            if let Some(opin) = self.inputs.get(&v) {
-                if pin != *opin {
-                    panic!("Variable {} is already used for pin {}, cannot assign to pin {}", v, opin, pin);
-                }
-            } else {
-                self.inputs.insert(v.clone(), *inum);
-            }
 
-            TruthTable::from(name)
+        if !INPUTS.contains_key(&pin) {
+            return Err(Error::IllegalInputPin(pin));
+        }
+
+        if let Some(opin) = self.inputs.get(&v) {
+            if pin != *opin {
+                return Err(Error::VariableReassigned{var: v, pin: *opin, new_pin: pin});
+            } else if active_low != self.active_low_inputs.contains(&v) {
+                return Err(Error::InputPolarityConflict{var: v, pin});
+            }
+        } else if let Some((ov, _)) = self.inputs.iter().find(|(_, opin)| **opin == pin) {
+            return Err(Error::InputPinReused{pin, var: ov.clone(), new_var: v});
         } else {
-            panic!("Cannot configure illegal input pin {}", pin);
+            self.fitted.take();
+            self.inputs.insert(v.clone(), pin);
+            if active_low {
+                self.active_low_inputs.insert(v.clone());
+            }
+            self.input_locations.insert(v, panic::Location::caller());
         }
+
+        Ok(TruthTable::from(name))
+    }
+
+    // pins[i] becomes bit i of the bus, named name[i]. If any pin is rejected none of them are declared.
+    #[track_caller]
+    pub fn input_bus(&mut self, pins: &[u32], name: &str) -> Result<Bus> {
+        let saved = self.save_inputs();
+        let mut bits = Vec::with_capacity(pins.len());
+
+        for (i, pin) in pins.iter().enumerate() {
+            match self.input(*pin, &format!("{}[{}]", name, i)) {
+                Ok(tt) => bits.push(tt),
+                Err(e) => {
+                    self.restore_inputs(saved);
+                    return Err(e);
+                },
+            }
+        }
+
+        Ok(Bus::new(name, bits))
+    }
+
+    // Declares an input bus whose unnamed codes never occur.
+    #[track_caller]
+    pub fn input_enum(&mut self, pins: &[u32], name: &str, codes: &[(&str, u64)]) -> Result<Symbolic> {
+        let saved = self.save_inputs();
+        let bus = self.input_bus(pins, name)?;
+        let symbolic = match Symbolic::new(bus, codes) {
+            Ok(symbolic) => symbolic,
+            Err(e) => {
+                self.restore_inputs(saved);
+                return Err(e);
+            },
+        };
+
+        self.care &= symbolic.valid();
+
+        Ok(symbolic)
     }
 
-    pub fn disable_output(&mut self, pin: u32) {
+    // Declares that tt holds for every input combination that can occur; the others are treated as
+    // don't-cares when minimizing every output.
+    #[track_caller]
+    pub fn assume(&mut self, tt: TruthTable) -> Result<()> {
+        let care = &self.care & &tt;
+
+        if care.canonical() == TruthTable::AlwaysFalse {
+            return Err(Error::ContradictoryAssumption);
+        }
+
+        self.fitted.take();
+        self.care = care;
+        self.assumptions.push((tt, panic::Location::caller()));
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn disable_output(&mut self, pin: u32) -> Result<()> {
         if !OUTPUTS.contains_key(&pin) {
-            panic!("Cannot configure illegal output pin {}", pin);
+            return Err(Error::IllegalOutputPin(pin));
         }
 
         if let Some(mode) = self.outputs.get(&pin) {
             match *mode {
                 OutputMode::Disabled => (),
-                _ => panic!("Cannot configure output pin {} already set with mode {}", pin, mode),
+                _ => return Err(Error::OutputModeConflict{pin, mode: mode.to_string()}),
             }
         } else {
+            self.fitted.take();
             self.outputs.insert(pin, OutputMode::Disabled);
+            self.output_locations.insert(pin, panic::Location::caller());
         }
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn combinatorial_output(&mut self, pin: u32, tt: TruthTable) -> Result<()> {
+        self.declare_combinatorial_output(pin, tt, false)
+    }
+
+    // The pin is driven low when tt is true; the inversion is done with the macrocell's polarity fuse.
+    #[track_caller]
+    pub fn combinatorial_output_active_low(&mut self, pin: u32, tt: TruthTable) -> Result<()> {
+        self.declare_combinatorial_output(pin, tt, true)
     }
 
-    pub fn combinatorial_output(&mut self, pin: u32, tt: TruthTable) {
+    #[track_caller]
+    fn declare_combinatorial_output(&mut self, pin: u32, tt: TruthTable, active_low: bool) -> Result<()> {
         if !OUTPUTS.contains_key(&pin) {
-            panic!("Cannot configure illegal output pin {}", pin);
+            return Err(Error::IllegalOutputPin(pin));
         }
 
         if let Some(mode) = self.outputs.get(&pin) {
             match mode {
-                OutputMode::Combinatorial{tt: ott, ..} => {
-                    if tt != *ott {
-                        panic!("Cannot configure output pin {} already set to a different truth table!\nCurrent table:\n{}\nWant to set:\n{}", pin, ott, tt);
+                OutputMode::Combinatorial{tt: ott, active_low: oactive_low} if *oactive_low == active_low => {
+                    if let Some(row) = tt.difference(ott) {
+                        let current = ott.eval(&row)?;
+                        let wanted = tt.eval(&row)?;
+                        return Err(Error::OutputFunctionConflict{pin, row, current, wanted});
                     }
                 },
-                _ => panic!("Cannot configure output pin {} already set with mode {}", pin, mode),
+                _ => return Err(Error::OutputModeConflict{pin, mode: mode.to_string()}),
             }
         } else {
-            self.outputs.insert(pin, OutputMode::Combinatorial{tt: tt});
+            self.fitted.take();
+            self.outputs.insert(pin, OutputMode::Combinatorial{tt, active_low});
+            self.output_locations.insert(pin, panic::Location::caller());
+        }
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn output_bus(&mut self, pins: &[u32], bus: Bus) -> Result<()> {
+        if pins.len() != bus.width() {
+            return Err(Error::BusWidth{bus: bus.to_string(), pins: pins.len()});
+        }
+
+        // If any pin is rejected none of them are declared.
+        let saved = (self.outputs.clone(), self.output_locations.clone());
+        for (pin, tt) in pins.iter().zip(bus.bits) {
+            if let Err(e) = self.combinatorial_output(*pin, tt) {
+                (self.outputs, self.output_locations) = saved;
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn output_polarity(&mut self, pin: u32, polarity: Polarity) -> Result<()> {
+        if !OUTPUTS.contains_key(&pin) {
+            return Err(Error::IllegalOutputPin(pin));
+        }
+
+        if let Some(current) = self.polarities.get(&pin) {
+            if *current != polarity {
+                return Err(Error::OutputPolarityConflict{pin, polarity: current.to_string()});
+            }
+        } else {
+            self.fitted.take();
+            self.polarities.insert(pin, polarity);
+        }
+
+        Ok(())
+    }
+
+    // Covers the output with extra consensus terms so that it does not glitch when a single input changes.
+    #[track_caller]
+    pub fn output_hazard_free(&mut self, pin: u32) -> Result<()> {
+        if !OUTPUTS.contains_key(&pin) {
+            return Err(Error::IllegalOutputPin(pin));
+        }
+
+        self.fitted.take();
+        self.hazard_free.insert(pin);
+
+        Ok(())
+    }
+
+    // Returns Positive or Negative as fitted, or None if the pin is not a combinatorial output that fits.
+    pub fn fitted_polarity(&self, pin: u32) -> Option<Polarity> {
+        self.fitted_product(pin).map(|prod| if prod.invert { Polarity::Negative } else { Polarity::Positive })
+    }
+
+    // Single-input transitions that can glitch the pin, or None if the pin is not a combinatorial output
+    // that fits. Transitions ruled out by input enums or assumptions are not reported. Input values and
+    // the hazard kind are pin levels, so active-low declarations are taken into account. As with
+    // Product::static_hazards(), dynamic hazards are not reported because the fitted logic cannot have any.
+    pub fn hazards(&self, pin: u32) -> Option<Vec<Hazard>> {
+        self.fitted_product(pin).map(|prod| prod.static_hazards_care(&self.care).into_iter().map(|mut hazard| {
+            for (v, value) in hazard.inputs.iter_mut() {
+                if self.active_low_inputs.contains(v) {
+                    *value = !*value;
+                }
+            }
+            hazard
+        }).collect())
+    }
+
+    fn fitted_product(&self, pin: u32) -> Option<&Product> {
+        self.fit().0.get(&pin).filter(|prod| prod.terms.len() < OUTPUTS[&pin].pts.len())
+    }
+
+    fn save_inputs(&self) -> (BTreeMap<Var, u32>, BTreeSet<Var>, BTreeMap<Var, Location>) {
+        (self.inputs.clone(), self.active_low_inputs.clone(), self.input_locations.clone())
+    }
+
+    fn restore_inputs(&mut self, saved: (BTreeMap<Var, u32>, BTreeSet<Var>, BTreeMap<Var, Location>)) {
+        (self.inputs, self.active_low_inputs, self.input_locations) = saved;
+    }
+
+    fn polarity(&self, pin: u32) -> Polarity {
+        self.polarities.get(&pin).copied().unwrap_or(Polarity::Auto)
+    }
+
+    fn pin_function(tt: &TruthTable, active_low: bool) -> TruthTable {
+        if active_low { !tt } else { tt.clone() }
+    }
+
+    fn estimate(&self, pin: u32, tt: &TruthTable, active_low: bool) -> usize {
+        let tt = GAL16V8::pin_function(tt, active_low);
+        match self.polarity(pin) {
+            Polarity::Positive => tt.estimate_terms_care(&self.care),
+            Polarity::Negative => (!&tt).estimate_terms_care(&self.care),
+            _ => tt.estimate_terms_care(&self.care).min((!&tt).estimate_terms_care(&self.care)),
+        }
+    }
+
+    fn product(&self, pin: u32, tt: &TruthTable, active_low: bool) -> Product {
+        let tt = GAL16V8::pin_function(tt, active_low);
+        let dnf = |tt: &TruthTable| {
+            if self.hazard_free.contains(&pin) { tt.dnf_hazard_free_care(&self.care) } else { tt.dnf_care(&self.care) }
+        };
+        let positive = || dnf(&tt);
+        let negative = || {
+            let mut prod = dnf(&!&tt);
+            prod.invert = !prod.invert;
+            prod
+        };
+        // A preferred polarity is only given up if it does not fit and the other one is smaller.
+        let prefer = |first: Product, second: &dyn Fn() -> Product| {
+            if first.terms.len() < OUTPUTS[&pin].pts.len() {
+                return first;
+            }
+            let second = second();
+            if second.terms.len() < first.terms.len() { second } else { first }
+        };
+
+        match self.polarity(pin) {
+            Polarity::Auto => {
+                let (pos, neg) = (positive(), negative());
+                if neg.terms.len() < pos.terms.len() { neg } else { pos }
+            },
+            Polarity::PreferPositive => prefer(positive(), &negative),
+            Polarity::PreferNegative => prefer(negative(), &positive),
+            Polarity::Positive => positive(),
+            Polarity::Negative => negative(),
+        }
+    }
+
+    // Minimization is slow, so the result is kept until the design changes.
+    fn fit(&self) -> &(BTreeMap<u32, Product>, Vec<Diagnostic>) {
+        self.fitted.get_or_init(|| self.fit_uncached())
+    }
+
+    fn fit_uncached(&self) -> (BTreeMap<u32, Product>, Vec<Diagnostic>) {
+        let mut products = BTreeMap::new();
+        let mut diagnostics = Vec::new();
+
+        for (tt, location) in &self.assumptions {
+            for v in tt.support() {
+                if !self.inputs.contains_key(&v) {
+                    diagnostics.push(Diagnostic{location, error: Error::UndeclaredAssumption(v)});
+                }
+            }
+        }
+
+        for (pin, mode) in &self.outputs {
+            if let OutputMode::Combinatorial{tt, active_low} = mode {
+                let location = self.output_locations[pin];
+                let has = OUTPUTS[pin].pts.len() - 1;
+
+                // Skip the expensive minimization when the function clearly cannot fit.
+                let estimate = self.estimate(*pin, tt, *active_low);
+                if estimate > has {
+                    diagnostics.push(Diagnostic{location, error: Error::TooManyTerms{pin: *pin, needs: estimate, estimated: true, has}});
+                    for v in tt.support() {
+                        if !self.inputs.contains_key(&v) {
+                            diagnostics.push(Diagnostic{location, error: Error::UndeclaredInput{pin: *pin, var: v}});
+                        }
+                    }
+                    continue;
+                }
+
+                let prod = self.product(*pin, tt, *active_low);
+
+                // Cheap compared to minimization, and catches mistakes in the cover or the polarity choice.
+                let pin_tt = GAL16V8::pin_function(tt, *active_low);
+                if let Some(row) = (prod.to_truthtable() & &self.care).difference(&(pin_tt & &self.care)) {
+                    diagnostics.push(Diagnostic{location, error: Error::ProductMismatch{pin: *pin, row}});
+                }
+
+                if prod.terms.len() > has {
+                    diagnostics.push(Diagnostic{location, error: Error::TooManyTerms{pin: *pin, needs: prod.terms.len(), estimated: false, has}});
+                }
+
+                for (i, v) in tt.vars().iter().enumerate() {
+                    if !self.inputs.contains_key(v) && prod.terms.iter().any(|term| term[i] != Factor::DontCare) {
+                        diagnostics.push(Diagnostic{location, error: Error::UndeclaredInput{pin: *pin, var: v.clone()}});
+                    }
+                }
+
+                products.insert(*pin, prod);
+            }
+        }
+
+        (products, diagnostics)
+    }
+
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        self.fit().1.clone()
+    }
+
+    // Pins that clearly cannot fit are skipped before minimization and so are left out; validate() reports them.
+    pub fn usage(&self) -> Vec<MacrocellUsage> {
+        self.fit().0.iter().map(|(&pin, prod)| MacrocellUsage {
+            pin,
+            polarity: if prod.invert { Polarity::Negative } else { Polarity::Positive },
+            terms: prod.term_count(),
+            available: OUTPUTS[&pin].pts.len() - 1,
+            literals: prod.literal_count(),
+        }).collect()
+    }
+
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+
+        for (v, pin) in &self.inputs {
+            let used = self.outputs.values().any(|mode| match mode {
+                OutputMode::Combinatorial{tt, ..} => tt.support().contains(v),
+                _ => false,
+            });
+            if !used {
+                lints.push(Lint{location: Some(self.input_locations[v]), warning: Warning::UnusedInput{pin: *pin, var: v.clone()}});
+            }
         }
+
+        // Outputs and inputs are compared by pin level, so active-low declarations are taken into account.
+        for (pin, mode) in &self.outputs {
+            if let OutputMode::Combinatorial{tt, active_low} = mode {
+                let location = Some(self.output_locations[pin]);
+                let tt = GAL16V8::pin_function(tt, *active_low);
+
+                match tt.canonical() {
+                    TruthTable::AlwaysTrue => lints.push(Lint{location, warning: Warning::ConstantOutput{pin: *pin, value: true}}),
+                    TruthTable::AlwaysFalse => lints.push(Lint{location, warning: Warning::ConstantOutput{pin: *pin, value: false}}),
+                    TruthTable::Explicit{..} => {
+                        for (other, omode) in self.outputs.range(.. pin) {
+                            if let OutputMode::Combinatorial{tt: ott, active_low: oactive_low} = omode {
+                                let ott = GAL16V8::pin_function(ott, *oactive_low);
+                                if tt == ott {
+                                    lints.push(Lint{location, warning: Warning::DuplicateOutput{pin: *pin, other: *other, inverted: false}});
+                                } else if tt == !ott {
+                                    lints.push(Lint{location, warning: Warning::DuplicateOutput{pin: *pin, other: *other, inverted: true}});
+                                }
+                            }
+                        }
+
+                        for v in self.inputs.keys() {
+                            let itt = GAL16V8::pin_function(&TruthTable::from(&*v.name), self.active_low_inputs.contains(v));
+                            if tt == itt {
+                                lints.push(Lint{location, warning: Warning::OutputFollowsInput{pin: *pin, var: v.clone(), inverted: false}});
+                            } else if tt == !itt {
+                                lints.push(Lint{location, warning: Warning::OutputFollowsInput{pin: *pin, var: v.clone(), inverted: true}});
+                            }
+                        }
+                    },
+                }
+            }
+        }
+
+        for pin in OUTPUTS.keys() {
+            if !self.outputs.contains_key(pin) {
+                lints.push(Lint{location: None, warning: Warning::DefaultMacrocell{pin: *pin}});
+            }
+        }
+
+        lints
     }
 
-    #[must_use]
-    pub fn write(&self, f: &mut dyn io::Write) -> io::Result<()> {
+    pub fn write(&self, f: &mut dyn io::Write) -> Result<Vec<Lint>> {
+        let (products, diagnostics) = self.fit();
+        if !diagnostics.is_empty() {
+            return Err(Error::Design(diagnostics.clone()));
+        }
+
         let mut checksum = num::Wrapping(0);
         fn write(checksum: &mut num::Wrapping<u16>, f: &mut dyn io::Write, s: String) -> io::Result<()> {
             for c in s.chars() {
@@ -165,22 +595,10 @@ impl GAL16V8 {
 
                     OutputMode::Disabled => out!("  *N Unused *L{:0>4} 1\n", fuses.ac1)?,
 
-                    OutputMode::Combinatorial{tt} => {
+                    OutputMode::Combinatorial{tt, ..} => {
                         out!("  *N Combinatorial *L{:0>4} 0\n", fuses.ac1)?;
 
-                        let prod;
-                        let pos_prod = tt.dnf();
-                        let mut neg_prod = (!tt).dnf();
-                        neg_prod.invert = !neg_prod.invert;
-                        if neg_prod.terms.len() < pos_prod.terms.len() {
-                            prod = neg_prod;
-                        } else {
-                            prod = pos_prod;
-                        }
-
-                        if prod.terms.len() >= fuses.pts.len() {
-                            panic!("Too many terms in product for this macrocell! (needs {}, has {})", prod.terms.len(), fuses.pts.len());
-                        }
+                        let prod = &products[pin];
 
                         if prod.invert {
                             out!("  *N Negative polarity *L{:0>4} 0\n", fuses.xor)?;
@@ -194,22 +612,13 @@ impl GAL16V8 {
                             let mut ordered_term: Vec<Factor> = (0..INPUTS.len()).map(|_| Factor::DontCare).collect();
 
                             for (i, factor) in term.iter().enumerate() {
-                                match factor {
-                                    Factor::DontCare => {},
-                                    Factor::IsFalse => {
-                                        if let Some(inum) = self.inputs.get(tt.var(i)) {
-                                            ordered_term[*inum as usize] = Factor::IsFalse;
-                                        } else {
-                                            panic!("Output pin {} depends on variable {} which is not an input", pin, tt.var(i));
-                                        }
-                                    },
-                                    Factor::IsTrue => {
-                                        if let Some(inum) = self.inputs.get(tt.var(i)) {
-                                            ordered_term[*inum as usize] = Factor::IsTrue;
-                                        } else {
-                                            panic!("Output pin {} depends on variable {} which is not an input", pin, tt.var(i));
-                                        }
-                                    },
+                                if *factor != Factor::DontCare {
+                                    let v = tt.var(i)?;
+                                    ordered_term[INPUTS[&self.inputs[v]] as usize] = match factor {
+                                        Factor::IsFalse if self.active_low_inputs.contains(v) => Factor::IsTrue,
+                                        Factor::IsTrue if self.active_low_inputs.contains(v) => Factor::IsFalse,
+                                        _ => *factor,
+                                    };
                                 }
                             }
 
@@ -233,8 +642,260 @@ impl GAL16V8 {
 
         out!("\n*N End of image.\n\n\x03")?;
 
-        write!(f, "{:0>4X}\n", checksum)?;
+        writeln!(f, "{:0>4X}", checksum)?;
 
-        Ok(())
+        Ok(self.lint())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jedec(chip: &GAL16V8) -> Vec<String> {
+        let mut out = Vec::new();
+        chip.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap().lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn rejected_declarations_are_not_kept() {
+        let mut chip = GAL16V8::new();
+        assert!(matches!(chip.input(20, "a"), Err(Error::IllegalInputPin(20))));
+        let a = chip.input(2, "a").unwrap();
+        assert!(matches!(chip.combinatorial_output(20, a.clone()), Err(Error::IllegalOutputPin(20))));
+        chip.combinatorial_output(19, a).unwrap();
+        assert!(chip.validate().is_empty());
+        assert!(chip.write(&mut Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn rejected_buses_declare_no_pins() {
+        let mut chip = GAL16V8::new();
+        chip.input(4, "x").unwrap();
+        assert!(chip.input_bus(&[2, 3, 4], "b").is_err());
+        assert!(chip.input_enum(&[2, 3], "s", &[("idle", 0), ("idle", 1)]).is_err());
+        // Pins 2 and 3 are still free, and so is pin 18 after the rejected output bus.
+        let c = chip.input_bus(&[2, 3], "c").unwrap();
+        assert!(chip.output_bus(&[18, 20], c.clone()).is_err());
+        chip.output_bus(&[19, 18], c).unwrap();
+        assert!(chip.write(&mut Vec::new()).is_ok());
+    }
+
+    // The JEDEC lines with each macrocell's terms as a sorted list of fuse patterns, since equivalent
+    // covers can list their terms in a different order.
+    fn fuses(chip: &GAL16V8) -> Vec<String> {
+        let mut lines = jedec(chip);
+        lines.pop();
+        let mut fuses: Vec<String> = Vec::new();
+        let mut terms = Vec::new();
+        for line in lines {
+            match line.rsplit_once(' ') {
+                Some((_, pattern)) if pattern.len() == 2 * INPUTS.len() => terms.push(pattern.to_string()),
+                _ => {
+                    terms.sort();
+                    fuses.append(&mut terms);
+                    fuses.push(line);
+                },
+            }
+        }
+        fuses
+    }
+
+    #[test]
+    fn active_low_input_fuses() {
+        let mut explicit = GAL16V8::new();
+        let p = explicit.input(2, "p").unwrap();
+        let q = explicit.input(3, "q").unwrap();
+        let r = explicit.input(4, "r").unwrap();
+        explicit.combinatorial_output(19, (!&p & &q) | &r).unwrap();
+        explicit.combinatorial_output(18, &p ^ &q).unwrap();
+
+        let mut active_low = GAL16V8::new();
+        let p = active_low.input_active_low(2, "p").unwrap();
+        let q = active_low.input(3, "q").unwrap();
+        let r = active_low.input(4, "r").unwrap();
+        active_low.combinatorial_output(19, (&p & &q) | &r).unwrap();
+        active_low.combinatorial_output(18, !&p ^ &q).unwrap();
+
+        assert_eq!(fuses(&explicit), fuses(&active_low));
+    }
+
+    #[test]
+    fn active_low_output_flips_polarity_fuse() {
+        let mut high = GAL16V8::new();
+        let a = high.input(2, "a").unwrap();
+        let b = high.input(3, "b").unwrap();
+        high.combinatorial_output(19, &a & &b).unwrap();
+
+        let mut low = GAL16V8::new();
+        let a = low.input(2, "a").unwrap();
+        let b = low.input(3, "b").unwrap();
+        low.combinatorial_output_active_low(19, &a & &b).unwrap();
+
+        // The same single term is used, so only the polarity fuse and the checksum change.
+        let (high, low) = (jedec(&high), jedec(&low));
+        let changed: Vec<_> = high.iter().zip(&low).filter(|(h, l)| h != l).collect();
+        let xor = OUTPUTS[&19].xor;
+        assert_eq!(changed.len(), 2);
+        assert_eq!(*changed[0].0, format!("  *N Positive polarity *L{:0>4} 1", xor));
+        assert_eq!(*changed[0].1, format!("  *N Negative polarity *L{:0>4} 0", xor));
+    }
+
+    #[test]
+    fn fitted_polarity() {
+        let mut chip = GAL16V8::new();
+        let bus = chip.input_bus(&[2, 3, 4, 5], "x").unwrap();
+        chip.combinatorial_output(12, TruthTable::parity(&bus.bits)).unwrap();
+        chip.combinatorial_output(19, &bus.bits[0] & &bus.bits[1]).unwrap();
+        assert_eq!(chip.fitted_polarity(12), None);
+        assert_eq!(chip.hazards(12), None);
+        assert_eq!(chip.fitted_polarity(19), Some(Polarity::Positive));
+
+        // Declarations made after a fit are taken into account.
+        chip.output_polarity(19, Polarity::Negative).unwrap();
+        assert_eq!(chip.fitted_polarity(19), Some(Polarity::Negative));
+        assert_eq!(chip.fitted_polarity(18), None);
+    }
+
+    #[test]
+    fn estimated_term_count_is_labelled() {
+        let mut chip = GAL16V8::new();
+        let bus = chip.input_bus(&[2, 3, 4, 5], "x").unwrap();
+        chip.combinatorial_output(12, TruthTable::parity(&bus.bits)).unwrap();
+        let diagnostics = chip.validate();
+        assert!(matches!(diagnostics[0].error, Error::TooManyTerms{pin: 12, needs: 8, estimated: true, has: 7}));
+        assert!(diagnostics[0].error.to_string().contains("needs at least 8"));
+    }
+
+    #[test]
+    fn unused_enum_codes_shrink_products() {
+        let codes = [("idle", 0), ("run", 1), ("stop", 2)];
+
+        let mut plain = GAL16V8::new();
+        let bus = plain.input_bus(&[2, 3, 4], "s").unwrap();
+        plain.combinatorial_output(19, bus.eq_const(2)).unwrap();
+
+        let mut chip = GAL16V8::new();
+        let s = chip.input_enum(&[2, 3, 4], "s", &codes).unwrap();
+        chip.combinatorial_output(19, s.is("stop").unwrap()).unwrap();
+
+        assert_eq!(plain.usage()[0].literals, 3);
+        assert_eq!(chip.usage()[0].literals, 1);
+
+        let duplicate = chip.input_enum(&[5, 6], "t", &[("run", 0), ("run", 1)]);
+        assert!(matches!(duplicate, Err(Error::DuplicateSymbol{ref symbol, ..}) if symbol == "run"));
+    }
+
+    #[test]
+    fn assumptions_shrink_products() {
+        let mut chip = GAL16V8::new();
+        let a = chip.input(2, "a").unwrap();
+        let b = chip.input(3, "b").unwrap();
+        let c = chip.input(4, "c").unwrap();
+        chip.combinatorial_output(19, TruthTable::parity(&[a.clone(), b.clone(), c.clone()])).unwrap();
+        assert_eq!(chip.usage()[0].terms, 4);
+
+        // With at most one input true, parity is a | b | c, whose complement /a & /b & /c is one term.
+        chip.assume(!((&a & &b) | (&a & &c) | (&b & &c))).unwrap();
+        assert_eq!(chip.usage()[0].terms, 1);
+        assert_eq!(chip.fitted_polarity(19), Some(Polarity::Negative));
+
+        assert!(matches!(chip.assume(&a & &b), Err(Error::ContradictoryAssumption)));
+        assert_eq!(chip.usage()[0].terms, 1);
+        assert!(chip.validate().is_empty());
+    }
+
+    fn warnings(chip: &GAL16V8) -> Vec<Warning> {
+        chip.lint().into_iter().map(|lint| lint.warning).filter(|w| !matches!(w, Warning::DefaultMacrocell{..})).collect()
+    }
+
+    #[test]
+    fn unused_input_lint() {
+        let mut chip = GAL16V8::new();
+        let a = chip.input(2, "a").unwrap();
+        chip.input(3, "b").unwrap();
+        let c = chip.input(4, "c").unwrap();
+        chip.combinatorial_output(19, &a & &c).unwrap();
+        assert_eq!(warnings(&chip), vec![Warning::UnusedInput{pin: 3, var: Var::from("b")}]);
+    }
+
+    #[test]
+    fn constant_output_lint() {
+        let mut chip = GAL16V8::new();
+        let a = chip.input(2, "a").unwrap();
+        chip.combinatorial_output(19, &a | &!&a).unwrap();
+        chip.combinatorial_output_active_low(18, &a | &!&a).unwrap();
+        assert_eq!(warnings(&chip), vec![
+            Warning::UnusedInput{pin: 2, var: Var::from("a")},
+            Warning::ConstantOutput{pin: 18, value: false},
+            Warning::ConstantOutput{pin: 19, value: true},
+        ]);
+    }
+
+    #[test]
+    fn duplicate_output_lint() {
+        let mut chip = GAL16V8::new();
+        let a = chip.input(2, "a").unwrap();
+        let b = chip.input(3, "b").unwrap();
+        chip.combinatorial_output(17, &a & &b).unwrap();
+        chip.combinatorial_output(18, &b & &a).unwrap();
+        chip.combinatorial_output_active_low(19, &a & &b).unwrap();
+        assert_eq!(warnings(&chip), vec![
+            Warning::DuplicateOutput{pin: 18, other: 17, inverted: false},
+            Warning::DuplicateOutput{pin: 19, other: 17, inverted: true},
+            Warning::DuplicateOutput{pin: 19, other: 18, inverted: true},
+        ]);
+    }
+
+    #[test]
+    fn output_follows_input_lint() {
+        let mut chip = GAL16V8::new();
+        let a = chip.input(2, "a").unwrap();
+        let b = chip.input_active_low(3, "b").unwrap();
+        chip.combinatorial_output(17, !&a).unwrap();
+        chip.combinatorial_output(18, a).unwrap();
+        // b is asserted when its pin is low, so driving it out on an active-high pin inverts the level.
+        chip.combinatorial_output(19, b).unwrap();
+        assert_eq!(warnings(&chip), vec![
+            Warning::OutputFollowsInput{pin: 17, var: Var::from("a"), inverted: true},
+            Warning::DuplicateOutput{pin: 18, other: 17, inverted: true},
+            Warning::OutputFollowsInput{pin: 18, var: Var::from("a"), inverted: false},
+            Warning::OutputFollowsInput{pin: 19, var: Var::from("b"), inverted: true},
+        ]);
+    }
+
+    #[test]
+    fn default_macrocell_lint() {
+        let mut chip = GAL16V8::new();
+        chip.disable_output(12).unwrap();
+        let a = chip.input(2, "a").unwrap();
+        chip.combinatorial_output(19, a).unwrap();
+        let pins: Vec<u32> = chip.lint().into_iter().filter_map(|lint| match lint.warning {
+            Warning::DefaultMacrocell{pin} => {
+                assert!(lint.location.is_none());
+                Some(pin)
+            },
+            _ => None,
+        }).collect();
+        assert_eq!(pins, vec![13, 14, 15, 16, 17, 18]);
+    }
+
+    #[test]
+    fn hazards_use_pin_levels() {
+        let mut chip = GAL16V8::new();
+        let sel = chip.input(2, "s").unwrap();
+        let a = chip.input_active_low(3, "a").unwrap();
+        let b = chip.input(4, "b").unwrap();
+        let f = TruthTable::ite(&sel, &a, &b);
+        chip.combinatorial_output(19, f.clone()).unwrap();
+        chip.combinatorial_output_active_low(18, f).unwrap();
+        chip.output_polarity(18, Polarity::Negative).unwrap();
+
+        // a is asserted, and so the hazard happens, while its pin is low.
+        let inputs: BTreeMap<Var, bool> = vec![(Var::from("a"), false), (Var::from("b"), true)].into_iter().collect();
+        assert_eq!(chip.hazards(19), Some(vec![Hazard{kind: HazardKind::Static1, var: Var::from("s"), inputs: inputs.clone()}]));
+        // The active-low pin is held low while the cover glitches, so it can briefly go high.
+        assert_eq!(chip.hazards(18), Some(vec![Hazard{kind: HazardKind::Static0, var: Var::from("s"), inputs}]));
     }
 }
@@ -0,0 +1,222 @@
+use itertools::Itertools;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::panic::Location;
+use std::rc::Rc;
+
+use crate::var::*;
+
+#[derive(Clone, Debug)]
+pub enum Error {
+    MissingVariable(Var),
+    NoSuchVariable(usize),
+    DuplicateVariable(Var),
+    LookupLength {
+        entries: usize,
+        inputs: usize,
+    },
+    LookupValue {
+        row: usize,
+        value: u64,
+        outputs: usize,
+    },
+    IllegalInputPin(u32),
+    IllegalOutputPin(u32),
+    VariableReassigned {
+        var: Var,
+        pin: u32,
+        new_pin: u32,
+    },
+    InputPinReused {
+        pin: u32,
+        var: Var,
+        new_var: Var,
+    },
+    InputPolarityConflict {
+        var: Var,
+        pin: u32,
+    },
+    OutputModeConflict {
+        pin: u32,
+        mode: String,
+    },
+    OutputPolarityConflict {
+        pin: u32,
+        polarity: String,
+    },
+    OutputFunctionConflict {
+        pin: u32,
+        row: BTreeMap<Var, bool>,
+        current: bool,
+        wanted: bool,
+    },
+    ProductMismatch {
+        pin: u32,
+        row: BTreeMap<Var, bool>,
+    },
+    UndeclaredInput {
+        pin: u32,
+        var: Var,
+    },
+    SymbolCode {
+        bus: String,
+        symbol: String,
+        code: u64,
+    },
+    DuplicateSymbol {
+        bus: String,
+        symbol: String,
+    },
+    UnknownSymbol {
+        bus: String,
+        symbol: String,
+    },
+    BusWidth {
+        bus: String,
+        pins: usize,
+    },
+    UndeclaredAssumption(Var),
+    ContradictoryAssumption,
+    TooManyTerms {
+        pin: u32,
+        needs: usize,
+        estimated: bool,
+        has: usize,
+    },
+    TooManyVariables {
+        count: usize,
+        max: usize,
+    },
+    Parse {
+        position: usize,
+        message: String,
+    },
+    Design(Vec<Diagnostic>),
+    Io(Rc<io::Error>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub location: &'static Location<'static>,
+    pub error: Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Warning {
+    UnusedInput {
+        pin: u32,
+        var: Var,
+    },
+    ConstantOutput {
+        pin: u32,
+        value: bool,
+    },
+    DuplicateOutput {
+        pin: u32,
+        other: u32,
+        inverted: bool,
+    },
+    OutputFollowsInput {
+        pin: u32,
+        var: Var,
+        inverted: bool,
+    },
+    DefaultMacrocell {
+        pin: u32,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Lint {
+    pub location: Option<&'static Location<'static>>,
+    pub warning: Warning,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingVariable(v) => write!(f, "No value given for variable {}", v),
+            Error::NoSuchVariable(i) => write!(f, "Truth table does not have variable {}", i),
+            Error::DuplicateVariable(v) => write!(f, "Variable {} appears more than once in truth table", v),
+            Error::LookupLength{entries, inputs} => write!(f, "Lookup table has {} entries, but {} inputs need {}", entries, inputs, 1u128 << inputs),
+            Error::LookupValue{row, value, outputs} => write!(f, "Lookup table entry {} has value {:#x}, which does not fit in {} outputs", row, value, outputs),
+            Error::IllegalInputPin(pin) => write!(f, "Cannot configure illegal input pin {}", pin),
+            Error::IllegalOutputPin(pin) => write!(f, "Cannot configure illegal output pin {}", pin),
+            Error::VariableReassigned{var, pin, new_pin} => write!(f, "Variable {} is already used for pin {}, cannot assign to pin {}", var, pin, new_pin),
+            Error::InputPinReused{pin, var, new_var} => write!(f, "Input pin {} is already used for variable {}, cannot assign to variable {}", pin, var, new_var),
+            Error::InputPolarityConflict{var, pin} => write!(f, "Variable {} is already used for pin {} with the opposite polarity", var, pin),
+            Error::OutputModeConflict{pin, mode} => write!(f, "Cannot configure output pin {} already set with mode {}", pin, mode),
+            Error::OutputPolarityConflict{pin, polarity} => write!(f, "Cannot configure output pin {} already set with {}", pin, polarity),
+            Error::OutputFunctionConflict{pin, row, current, wanted} => {
+                let inputs = row.iter().map(|(v, b)| format!("{}={}", v, *b as u8)).join(" ");
+                write!(f, "Cannot configure output pin {} already set to a different truth table! With inputs {} the current table gives {} but the new table gives {}",
+                    pin, inputs, *current as u8, *wanted as u8)
+            },
+            Error::ProductMismatch{pin, row} => {
+                let inputs = row.iter().map(|(v, b)| format!("{}={}", v, *b as u8)).join(" ");
+                write!(f, "Product terms fitted for output pin {} do not implement its truth table with inputs {}", pin, inputs)
+            },
+            Error::UndeclaredInput{pin, var} => write!(f, "Output pin {} depends on variable {} which is not an input", pin, var),
+            Error::SymbolCode{bus, symbol, code} => write!(f, "Code {} for {} does not fit in bus {}", code, symbol, bus),
+            Error::DuplicateSymbol{bus, symbol} => write!(f, "Symbol {} is given more than one code in bus {}", symbol, bus),
+            Error::UnknownSymbol{bus, symbol} => write!(f, "Bus {} has no value named {}", bus, symbol),
+            Error::BusWidth{bus, pins} => write!(f, "Cannot assign bus {} to {} pins", bus, pins),
+            Error::UndeclaredAssumption(var) => write!(f, "Assumption depends on variable {} which is not an input", var),
+            Error::ContradictoryAssumption => write!(f, "Assumption rules out every remaining input combination"),
+            Error::TooManyTerms{pin, needs, estimated, has} => write!(f, "Too many terms in product for the macrocell on pin {}! (needs {}{}, has {})", pin, if *estimated { "at least " } else { "" }, needs, has),
+            Error::TooManyVariables{count, max} => write!(f, "Truth table has {} variables, but at most {} are supported here", count, max),
+            Error::Parse{position, message} => write!(f, "Parse error at offset {}: {}", position, message),
+            Error::Design(ds) => {
+                write!(f, "Design has {} problem{}:", ds.len(), if ds.len() == 1 { "" } else { "s" })?;
+                for d in ds {
+                    write!(f, "\n  {}", d)?;
+                }
+                Ok(())
+            },
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(&**e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error { Error::Io(Rc::new(e)) }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.error)
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::UnusedInput{pin, var} => write!(f, "Input {} on pin {} is not used by any output", var, pin),
+            Warning::ConstantOutput{pin, value} => write!(f, "Output pin {} is always {}", pin, *value as u8),
+            Warning::DuplicateOutput{pin, other, inverted} => write!(f, "Output pin {} is {} output pin {}", pin, if *inverted { "the complement of" } else { "identical to" }, other),
+            Warning::OutputFollowsInput{pin, var, inverted} => write!(f, "Output pin {} is {} input {}", pin, if *inverted { "the complement of" } else { "identical to" }, var),
+            Warning::DefaultMacrocell{pin} => write!(f, "Macrocell for pin {} is not configured and is left driving the pin; use disable_output if the pin is unused", pin),
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(location) = self.location {
+            write!(f, "{}: ", location)?;
+        }
+        write!(f, "warning: {}", self.warning)
+    }
+}
@@ -0,0 +1,183 @@
+use itertools::Itertools;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::truthtable::*;
+use crate::var::*;
+
+// For single-input changes a two-level sum of products can only have static-1 hazards, which become
+// static-0 hazards when the polarity is negative. Dynamic hazards need multi-level logic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HazardKind {
+    Static0,
+    Static1,
+}
+
+// A single-input transition during which the output can glitch: var changes while the other inputs
+// hold the given values, and the two rows are covered by different terms.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    pub var: Var,
+    pub inputs: BTreeMap<Var, bool>,
+}
+
+impl fmt::Display for HazardKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HazardKind::Static0 => write!(f, "static-0"),
+            HazardKind::Static1 => write!(f, "static-1"),
+        }
+    }
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} hazard: {} changing", self.kind, self.var)?;
+        if !self.inputs.is_empty() {
+            let inputs = self.inputs.iter().map(|(v, b)| format!("{}={}", v, *b as u8)).join(" ");
+            write!(f, " while {}", inputs)?;
+        }
+        Ok(())
+    }
+}
+
+// The care set restricted to the given variables, as a table over exactly those variables. A row is
+// kept if it is in the care set for any value of the other variables.
+fn project_care(vars: &[Var], care: &TruthTable) -> TruthTable {
+    let care = care.vars().iter().filter(|v| !vars.contains(v)).fold(care.clone(), |care, v| care.exists(v));
+    TruthTable::tabulate(vars, |row| {
+        let assignment = vars.iter().enumerate().map(|(i, v)| (v.clone(), row & (1 << i) != 0)).collect();
+        care.eval(&assignment).unwrap_or(true)
+    })
+}
+
+impl Product {
+    // Only static hazards are reported. A dynamic hazard needs a path through more than two levels of
+    // logic, so a sum of products cannot have one for a single-input change.
+    pub fn static_hazards(&self) -> Vec<Hazard> {
+        self.static_hazards_care(&TruthTable::always())
+    }
+
+    // Transitions involving a row outside the care set are assumed never to happen.
+    pub fn static_hazards_care(&self, care: &TruthTable) -> Vec<Hazard> {
+        let care = project_care(&self.vars, care);
+        self.hazard_rows(&|row| care.eval_bits(row)).into_iter().map(|(i, row)| self.hazard(i, row)).collect()
+    }
+
+    // Pairs (i, row) where row and row | 1 << i are both true but no single term covers both.
+    fn hazard_rows(&self, possible: &dyn Fn(u64) -> bool) -> Vec<(usize, u64)> {
+        let mut hazards = Vec::new();
+
+        for row in 0 .. 1u64 << self.vars.len() {
+            if !possible(row) || !self.terms.iter().any(|term| covers(term, row)) {
+                continue;
+            }
+            for i in 0 .. self.vars.len() {
+                let other = row | (1 << i);
+                if other == row || !possible(other) || !self.terms.iter().any(|term| covers(term, other)) {
+                    continue;
+                }
+                if !self.terms.iter().any(|term| term[i] == Factor::DontCare && covers(term, row)) {
+                    hazards.push((i, row));
+                }
+            }
+        }
+
+        hazards
+    }
+
+    fn hazard(&self, i: usize, row: u64) -> Hazard {
+        let inputs = self.vars.iter().enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(j, v)| (v.clone(), row & (1 << j) != 0))
+            .collect();
+        let kind = if self.invert { HazardKind::Static0 } else { HazardKind::Static1 };
+        Hazard { kind, var: self.vars[i].clone(), inputs }
+    }
+}
+
+impl TruthTable {
+    pub fn dnf_hazard_free(&self) -> Product {
+        self.dnf_hazard_free_care(&TruthTable::always())
+    }
+
+    // Adds a consensus term for every static-1 hazard between two care rows.
+    pub fn dnf_hazard_free_care(&self, care: &TruthTable) -> Product {
+        let mut prod = self.dnf_care(care);
+        let vars = prod.vars.clone();
+
+        let care = project_care(&vars, care);
+        let off = |row| !self.eval_bits(row) && care.eval_bits(row);
+
+        while let Some(&(i, row)) = prod.hazard_rows(&|row| care.eval_bits(row)).first() {
+            let mut term: Vec<Factor> = (0 .. vars.len()).map(|j| if row & (1 << j) != 0 { Factor::IsTrue } else { Factor::IsFalse }).collect();
+            term[i] = Factor::DontCare;
+
+            // Grow the term as far as possible without covering an off row, like the other terms.
+            for j in 0 .. vars.len() {
+                let factor = term[j];
+                term[j] = Factor::DontCare;
+                if (0 .. 1u64 << vars.len()).any(|row| covers(&term, row) && off(row)) {
+                    term[j] = factor;
+                }
+            }
+
+            prod.terms.push(term);
+        }
+
+        prod
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(name: &str) -> TruthTable { TruthTable::from(name) }
+
+    #[test]
+    fn mux_gets_consensus_term() {
+        let tt = TruthTable::ite(&v("s"), &v("a"), &v("b"));
+
+        let hazards = tt.dnf().static_hazards();
+        assert_eq!(hazards.len(), 1);
+        assert_eq!(hazards[0].kind, HazardKind::Static1);
+        assert_eq!(hazards[0].var, Var::from("s"));
+        assert_eq!(hazards[0].inputs, [(Var::from("a"), true), (Var::from("b"), true)].iter().cloned().collect());
+
+        let prod = tt.dnf_hazard_free();
+        assert!(prod.implements(&tt));
+        assert!(prod.static_hazards().is_empty());
+        assert_eq!(prod.terms.len(), 3);
+    }
+
+    #[test]
+    fn hazard_free_covers_are_equivalent() {
+        for s in ["a & /b & c | b & d | /c & /d & e", "a ^ b ^ c", "a & b | /a & c | b & /c & d", "a | /a"] {
+            let tt = TruthTable::parse(s).unwrap();
+            let prod = tt.dnf_hazard_free();
+            assert!(prod.implements(&tt), "{}", s);
+            assert!(prod.static_hazards().is_empty(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn care_set_limits_consensus_terms() {
+        let tt = TruthTable::ite(&v("s"), &v("a"), &v("b"));
+        let care = !(v("a") & v("b"));
+
+        let prod = tt.dnf_hazard_free_care(&care);
+        assert!(prod.implements_care(&tt, &care));
+        assert_eq!(prod.terms.len(), 2);
+        assert!(prod.static_hazards_care(&care).is_empty());
+        assert!(!prod.static_hazards().is_empty());
+    }
+
+    #[test]
+    fn negative_polarity_gives_static_0() {
+        let mut prod = TruthTable::ite(&v("s"), &v("a"), &v("b")).dnf();
+        prod.invert = true;
+        assert_eq!(prod.static_hazards()[0].kind, HazardKind::Static0);
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+
+use crate::error::*;
+use crate::truthtable::*;
+
+const MAX_VARS: usize = 6;
+
+// The first half of the variables select the row and the rest select the column, both in Gray code
+// order so that adjacent cells differ in exactly one variable.
+pub struct KarnaughMap<'a> {
+    tt: &'a TruthTable,
+    cover: Option<&'a Product>,
+    terms: Vec<Vec<(usize, bool)>>,
+}
+
+fn gray(i: usize) -> usize { i ^ (i >> 1) }
+
+fn label(g: usize, n: usize) -> String {
+    (0 .. n).rev().map(|j| if g & (1 << j) != 0 { '1' } else { '0' }).collect()
+}
+
+impl KarnaughMap<'_> {
+    // Terms are lettered while the letters last, and numbered from 1 after that.
+    fn term_name(&self, i: usize) -> String {
+        if self.terms.len() <= 26 { ((b'A' + i as u8) as char).to_string() } else { (i + 1).to_string() }
+    }
+}
+
+impl TruthTable {
+    pub fn kmap(&self) -> Result<KarnaughMap<'_>> {
+        if self.vars().len() > MAX_VARS {
+            return Err(Error::TooManyVariables{count: self.vars().len(), max: MAX_VARS});
+        }
+
+        Ok(KarnaughMap {
+            tt: self,
+            cover: None,
+            terms: Vec::new(),
+        })
+    }
+
+    // Each term of the cover is given a name, which is shown in every cell the term covers.
+    pub fn kmap_with_cover<'a>(&'a self, cover: &'a Product) -> Result<KarnaughMap<'a>> {
+        let mut kmap = self.kmap()?;
+
+        for term in &cover.terms {
+            let mut literals = Vec::new();
+            for (v, b) in cover.literals(term) {
+                match self.vars().iter().position(|w| w == v) {
+                    Some(k) => literals.push((k, b)),
+                    None => return Err(Error::MissingVariable(v.clone())),
+                }
+            }
+            kmap.terms.push(literals);
+        }
+
+        kmap.cover = Some(cover);
+        Ok(kmap)
+    }
+}
+
+impl fmt::Display for KarnaughMap<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vars = self.tt.vars();
+        let r = vars.len() / 2;
+        let c = vars.len() - r;
+
+        let row_names = vars[.. r].iter().rev().map(|v| v.to_string()).collect::<Vec<String>>().join(" ");
+        let col_names = vars[r ..].iter().rev().map(|v| v.to_string()).collect::<Vec<String>>().join(" ");
+
+        let cell = |x: usize| {
+            let mut s = String::new();
+            s.push(if self.tt.eval_bits(x as u64) { '1' } else { '0' });
+            let names: Vec<String> = self.terms.iter().enumerate()
+                .filter(|(_, t)| t.iter().all(|(k, b)| (x & (1 << k) != 0) == *b))
+                .map(|(i, _)| self.term_name(i))
+                .collect();
+            if !names.is_empty() {
+                s.push(' ');
+                s.push_str(&names.join(if self.terms.len() <= 26 { "" } else { "," }));
+            }
+            s
+        };
+
+        let cells: Vec<Vec<String>> = (0 .. 1 << r).map(|i| (0 .. 1 << c).map(|j| cell(gray(i) | (gray(j) << r))).collect()).collect();
+
+        let corner = format!("{} \\ {}", row_names, col_names);
+        let hw = corner.len().max(r);
+        let cw = cells.iter().flatten().map(|s| s.len()).max().unwrap_or(0).max(c);
+
+        write!(f, " {: >hw$} |", corner, hw=hw)?;
+        for j in 0 .. 1 << c {
+            write!(f, " {: >cw$}", label(gray(j), c), cw=cw)?;
+        }
+        writeln!(f)?;
+        write!(f, "-{:->hw$}-+", "", hw=hw)?;
+        for _ in 0 .. 1 << c {
+            write!(f, "-{:->cw$}", "", cw=cw)?;
+        }
+        for (i, row) in cells.iter().enumerate() {
+            writeln!(f)?;
+            write!(f, " {: >hw$} |", label(gray(i), r), hw=hw)?;
+            for s in row {
+                write!(f, " {: >cw$}", s, cw=cw)?;
+            }
+        }
+
+        if let Some(cover) = self.cover {
+            if cover.invert {
+                write!(f, "\nNegative polarity: the terms cover the cells where the output is 0")?;
+            }
+            for (i, term) in cover.terms.iter().enumerate() {
+                let literals: Vec<String> = cover.literals(term).map(|(v, b)| format!("{}{}", if b { "" } else { "/" }, v)).collect();
+                write!(f, "\n {}: {}", self.term_name(i), if literals.is_empty() { "1".to_string() } else { literals.join(" & ") })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::var::Var;
+
+    #[test]
+    fn many_terms_are_numbered() {
+        let vars: Vec<Var> = (0 .. 6).map(|i| Var::from(&*format!("x{}", i))).collect();
+        let parity = TruthTable::from_fn(&vars, |row| row.count_ones() % 2 == 1).unwrap();
+        // One term per true row, more than there are letters.
+        let terms = (0 .. 64u64).filter(|row| row.count_ones() % 2 == 1)
+            .map(|row| (0 .. 6).map(|i| if row & (1 << i) != 0 { Factor::IsTrue } else { Factor::IsFalse }).collect())
+            .collect();
+        let cover = Product { invert: false, vars: vars.clone(), terms };
+
+        let map = parity.kmap_with_cover(&cover).unwrap().to_string();
+        assert!(map.contains("\n 1: ") && map.contains("\n 32: "));
+        assert!(!map.contains('['));
+
+        let small = parity.restrict(&vars[5], false).restrict(&vars[4], false).restrict(&vars[3], false).restrict(&vars[2], false);
+        let map = small.kmap_with_cover(&small.dnf()).unwrap().to_string();
+        assert!(map.contains("\n A: ") && map.contains("\n B: "));
+    }
+}
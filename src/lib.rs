@@ -1,6 +1,17 @@
+mod blocks;
+mod bus;
 pub mod dev;
+mod error;
+mod hazard;
+mod kmap;
+mod parse;
 mod truthtable;
 mod var;
 
-pub use truthtable::TruthTable;
+pub use blocks::{decoder, mux, priority_encoder};
+pub use bus::{Bus, Symbolic};
+pub use error::{Diagnostic, Error, Lint, Result, Warning};
+pub use hazard::{Hazard, HazardKind};
+pub use kmap::KarnaughMap;
+pub use truthtable::{Factor, Product, ProductOfSums, TruthTable};
 pub use var::Var;
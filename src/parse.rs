@@ -0,0 +1,173 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::error::*;
+use crate::truthtable::*;
+
+// Precedence from loosest to tightest: | (or +), ^, & (or *), then prefix ! (or ~ or /).
+
+struct Parser<'a> {
+    src: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+fn is_ident_start(c: char) -> bool { c.is_ascii_alphabetic() || c == '_' }
+fn is_ident(c: char) -> bool { c.is_ascii_alphanumeric() || c == '_' || c == '[' || c == ']' || c == '#' }
+
+impl<'a> Parser<'a> {
+    fn skip_space(&mut self) {
+        while let Some((_, c)) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<(usize, char)> {
+        self.skip_space();
+        self.chars.peek().cloned()
+    }
+
+    fn accept(&mut self, ops: &[char]) -> bool {
+        match self.peek() {
+            Some((_, c)) if ops.contains(&c) => {
+                self.chars.next();
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn error<T>(&mut self, message: &str) -> Result<T> {
+        let position = self.peek().map(|(i, _)| i).unwrap_or(self.src.len());
+        Err(Error::Parse{position, message: message.to_string()})
+    }
+
+    fn or(&mut self) -> Result<TruthTable> {
+        let mut tt = self.xor()?;
+        while self.accept(&['|', '+']) {
+            tt |= self.xor()?;
+        }
+        Ok(tt)
+    }
+
+    fn xor(&mut self) -> Result<TruthTable> {
+        let mut tt = self.and()?;
+        while self.accept(&['^']) {
+            tt ^= self.and()?;
+        }
+        Ok(tt)
+    }
+
+    fn and(&mut self) -> Result<TruthTable> {
+        let mut tt = self.not()?;
+        while self.accept(&['&', '*']) {
+            tt &= self.not()?;
+        }
+        Ok(tt)
+    }
+
+    fn not(&mut self) -> Result<TruthTable> {
+        if self.accept(&['!', '~', '/']) {
+            Ok(!self.not()?)
+        } else {
+            self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<TruthTable> {
+        match self.peek() {
+            Some((_, '(')) => {
+                self.chars.next();
+                let tt = self.or()?;
+                if !self.accept(&[')']) {
+                    return self.error("expected )");
+                }
+                Ok(tt)
+            },
+            Some((_, '0')) => {
+                self.chars.next();
+                Ok(TruthTable::never())
+            },
+            Some((_, '1')) => {
+                self.chars.next();
+                Ok(TruthTable::always())
+            },
+            Some((start, c)) if is_ident_start(c) => {
+                let mut end = start;
+                while let Some((i, c)) = self.chars.peek().cloned() {
+                    if !is_ident(c) {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    self.chars.next();
+                }
+                Ok(TruthTable::from(&self.src[start .. end]))
+            },
+            Some(_) => self.error("expected variable, constant or ("),
+            None => self.error("unexpected end of expression"),
+        }
+    }
+}
+
+impl TruthTable {
+    pub fn parse(s: &str) -> Result<TruthTable> {
+        let mut p = Parser {
+            src: s,
+            chars: s.char_indices().peekable(),
+        };
+
+        let tt = p.or()?;
+
+        if p.peek().is_some() {
+            return p.error("expected operator");
+        }
+
+        Ok(tt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(name: &str) -> TruthTable { TruthTable::from(name) }
+
+    fn position(s: &str) -> usize {
+        match TruthTable::parse(s) {
+            Err(Error::Parse{position, ..}) => position,
+            other => panic!("expected a parse error for {:?}, got {:?}", s, other),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(TruthTable::parse("a | b & c").unwrap(), v("a") | (v("b") & v("c")));
+        assert_eq!(TruthTable::parse("a ^ b & c").unwrap(), v("a") ^ (v("b") & v("c")));
+        assert_eq!(TruthTable::parse("a | b ^ c").unwrap(), v("a") | (v("b") ^ v("c")));
+        assert_eq!(TruthTable::parse("!a & b").unwrap(), !v("a") & v("b"));
+        assert_eq!(TruthTable::parse("!(a & b)").unwrap(), !(v("a") & v("b")));
+        assert_eq!(TruthTable::parse("a + b * c").unwrap(), TruthTable::parse("a | b & c").unwrap());
+    }
+
+    #[test]
+    fn prefix_not_and_names() {
+        assert_eq!(TruthTable::parse("/a").unwrap(), !v("a"));
+        assert_eq!(TruthTable::parse("~/a").unwrap(), v("a"));
+        assert_eq!(TruthTable::parse("/bit[3] & rin#").unwrap(), !v("bit[3]") & v("rin#"));
+        assert_eq!(TruthTable::parse("_x1").unwrap(), v("_x1"));
+        assert_eq!(TruthTable::parse("a & 1 | 0").unwrap(), v("a"));
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(position("a / b"), 2);
+        assert_eq!(position("a &"), 3);
+        assert_eq!(position("(a | b"), 6);
+        assert_eq!(position("a & )"), 4);
+        assert_eq!(position("a b"), 2);
+        assert_eq!(position(""), 0);
+    }
+}
@@ -1,8 +1,10 @@
 use itertools::Itertools;
 use std::collections::BTreeMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops;
 
+use crate::error::*;
 use crate::var::*;
 
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
@@ -15,9 +17,13 @@ pub enum Factor {
 #[derive(Clone, Debug)]
 pub struct Product {
     pub invert: bool,
+    pub vars: Vec<Var>,
     pub terms: Vec<Vec<Factor>>,
 }
 
+// Holds a sum-of-products cover of the complement; each of its terms is one sum by De Morgan's law.
+pub struct ProductOfSums(Product);
+
 #[derive(Clone, Debug)]
 pub enum TruthTable {
     AlwaysTrue,
@@ -32,9 +38,89 @@ impl TruthTable {
     pub fn always() -> TruthTable { TruthTable::AlwaysTrue }
     pub fn never() -> TruthTable { TruthTable::AlwaysFalse }
 
+    fn explicit(vars: Vec<Var>, table: Vec<bool>) -> TruthTable {
+        if vars.is_empty() {
+            if table[0] { TruthTable::AlwaysTrue } else { TruthTable::AlwaysFalse }
+        } else {
+            TruthTable::Explicit { vars, table }
+        }
+    }
+
+    // Bit i of the closure argument is the value of vars[i].
+    pub fn from_fn<F: Fn(u64) -> bool>(vars: &[Var], f: F) -> Result<TruthTable> {
+        for (i, v) in vars.iter().enumerate() {
+            if vars[.. i].contains(v) {
+                return Err(Error::DuplicateVariable(v.clone()));
+            }
+        }
+
+        Ok(TruthTable::tabulate(vars, f))
+    }
+
+    // Like from_fn, for callers whose variables are already known to be distinct.
+    pub(crate) fn tabulate<F: Fn(u64) -> bool>(vars: &[Var], f: F) -> TruthTable {
+        let table = (0 .. 1 << vars.len()).map(f).collect();
+
+        TruthTable::explicit(vars.to_vec(), table)
+    }
+
+    // Entry x of the lookup table holds the outputs for input row x, one output per bit.
+    pub fn from_lookup(inputs: &[Var], lookup: &[u64], outputs: usize) -> Result<Vec<TruthTable>> {
+        if inputs.len() >= 64 || lookup.len() != 1 << inputs.len() {
+            return Err(Error::LookupLength{entries: lookup.len(), inputs: inputs.len()});
+        }
+        if let Some((row, value)) = lookup.iter().enumerate().find(|(_, x)| x.checked_shr(outputs as u32).unwrap_or(0) != 0) {
+            return Err(Error::LookupValue{row, value: *value, outputs});
+        }
+
+        (0 .. outputs).map(|b| TruthTable::from_fn(inputs, |x| lookup[x as usize].checked_shr(b as u32).unwrap_or(0) & 1 != 0)).collect()
+    }
+
+    pub fn ite(cond: &TruthTable, then: &TruthTable, otherwise: &TruthTable) -> TruthTable {
+        tableop(&[cond, then, otherwise], &|x| if x[0] { x[1] } else { x[2] })
+    }
+
+    pub fn implies(&self, that: &TruthTable) -> TruthTable {
+        bitop(self, that, &|a, b| !a | b)
+    }
+
+    pub fn iff(&self, that: &TruthTable) -> TruthTable {
+        bitop(self, that, &|a, b| a == b)
+    }
+
+    pub fn parity(inputs: &[TruthTable]) -> TruthTable {
+        inputs.iter().fold(TruthTable::never(), |tt, x| tt ^ x)
+    }
+
+    pub fn at_least(k: usize, inputs: &[TruthTable]) -> TruthTable {
+        // counts[j] is true when at least j of the inputs seen so far are true
+        let mut counts = vec![TruthTable::never(); k + 1];
+        counts[0] = TruthTable::always();
+
+        for x in inputs {
+            for j in (1 ..= k).rev() {
+                counts[j] = &counts[j] | (&counts[j - 1] & x);
+            }
+        }
+
+        counts.pop().unwrap()
+    }
+
+    pub fn exactly(k: usize, inputs: &[TruthTable]) -> TruthTable {
+        TruthTable::at_least(k, inputs) & !TruthTable::at_least(k + 1, inputs)
+    }
+
+    pub fn majority(inputs: &[TruthTable]) -> TruthTable {
+        TruthTable::at_least(inputs.len() / 2 + 1, inputs)
+    }
+
+    pub fn one_hot(inputs: &[TruthTable]) -> TruthTable {
+        TruthTable::exactly(1, inputs)
+    }
+
     fn true_rows(&self) -> usize {
         match self {
-            TruthTable::AlwaysTrue => 0,
+            TruthTable::AlwaysTrue => 1,
             TruthTable::AlwaysFalse => 0,
             TruthTable::Explicit{table, ..} => {
                 let mut n = 0;
@@ -46,25 +132,233 @@ impl TruthTable {
         }
     }
 
-    pub fn var(&self, i: usize) -> &Var {
+    // Number of true rows over the variables the function depends on, so equal tables give equal counts
+    // and a constant true table has one row.
+    pub fn count_true(&self) -> usize {
+        self.canonical().true_rows()
+    }
+
+    pub fn var(&self, i: usize) -> Result<&Var> {
+        self.vars().get(i).ok_or(Error::NoSuchVariable(i))
+    }
+
+    pub fn vars(&self) -> &[Var] {
         match self {
-            TruthTable::AlwaysTrue => panic!("Truth table does not have variable {}", i),
-            TruthTable::AlwaysFalse => panic!("Truth table does not have variable {}", i),
-            TruthTable::Explicit{vars, ..} => &vars[i],
+            TruthTable::AlwaysTrue => &[],
+            TruthTable::AlwaysFalse => &[],
+            TruthTable::Explicit{vars, ..} => vars,
+        }
+    }
+
+    pub fn eval(&self, assignment: &BTreeMap<Var, bool>) -> Result<bool> {
+        let mut bits = 0;
+
+        for (i, v) in self.vars().iter().enumerate() {
+            match assignment.get(v) {
+                Some(true) => bits |= 1 << i,
+                Some(false) => {},
+                None => return Err(Error::MissingVariable(v.clone())),
+            }
         }
+
+        Ok(self.eval_bits(bits))
     }
 
+    // Bit i of the argument is the value of var(i); bits beyond the last variable are ignored.
+    pub fn eval_bits(&self, bits: u64) -> bool {
+        match self {
+            TruthTable::AlwaysTrue => true,
+            TruthTable::AlwaysFalse => false,
+            TruthTable::Explicit{table, ..} => table[bits as usize & (table.len() - 1)],
+        }
+    }
+
+    pub fn restrict(&self, var: &Var, value: bool) -> TruthTable {
+        match self {
+            TruthTable::Explicit{vars, table} => {
+                if let Some(k) = vars.iter().position(|v| v == var) {
+                    let low = (1 << k) - 1;
+                    let bit = if value { 1 << k } else { 0 };
+
+                    let vars = vars.iter().filter(|v| *v != var).cloned().collect();
+                    let table = (0 .. table.len() / 2).map(|x| table[(x & low) | ((x & !low) << 1) | bit]).collect();
+
+                    TruthTable::explicit(vars, table)
+                } else {
+                    self.clone()
+                }
+            },
+            _ => self.clone(),
+        }
+    }
+
+    pub fn compose(&self, var: &Var, that: &TruthTable) -> TruthTable {
+        if !self.vars().contains(var) {
+            return self.clone();
+        }
+
+        TruthTable::ite(that, &self.restrict(var, true), &self.restrict(var, false))
+    }
+
+    // Variables renamed to the same name are merged into a single variable.
+    pub fn rename(&self, map: &BTreeMap<Var, Var>) -> TruthTable {
+        match self {
+            TruthTable::Explicit{vars, table} => {
+                let mut new_vars: Vec<Var> = Vec::new();
+                let mut index = Vec::with_capacity(vars.len());
+
+                for v in vars {
+                    let n = map.get(v).unwrap_or(v);
+                    if let Some(j) = new_vars.iter().position(|x| x == n) {
+                        index.push(j);
+                    } else {
+                        index.push(new_vars.len());
+                        new_vars.push(n.clone());
+                    }
+                }
+
+                let table = (0 .. 1 << new_vars.len()).map(|x| {
+                    let mut r = 0;
+                    for (i, j) in index.iter().enumerate() {
+                        if x & (1 << j) != 0 {
+                            r |= 1 << i;
+                        }
+                    }
+                    table[r]
+                }).collect();
+
+                TruthTable::Explicit {
+                    vars: new_vars,
+                    table,
+                }
+            },
+            _ => self.clone(),
+        }
+    }
+
+    pub fn exists(&self, var: &Var) -> TruthTable {
+        self.restrict(var, false) | self.restrict(var, true)
+    }
+
+    pub fn forall(&self, var: &Var) -> TruthTable {
+        self.restrict(var, false) & self.restrict(var, true)
+    }
+
+    fn depends_on(&self, k: usize) -> bool {
+        match self {
+            TruthTable::Explicit{table, ..} => (0 .. table.len()).any(|x| x & (1 << k) == 0 && table[x] != table[x | (1 << k)]),
+            _ => false,
+        }
+    }
+
+    pub fn support(&self) -> Vec<Var> {
+        self.vars().iter().enumerate().filter(|(k, _)| self.depends_on(*k)).map(|(_, v)| v.clone()).collect()
+    }
+
+    pub fn drop_redundant_vars(&self) -> TruthTable {
+        let mut tt = self.clone();
+
+        for (k, v) in self.vars().iter().enumerate() {
+            if !self.depends_on(k) {
+                tt = tt.restrict(v, false);
+            }
+        }
+
+        tt
+    }
+
+    // Redundant variables removed and the rest in sorted order, so equal functions have identical representations.
+    pub fn canonical(&self) -> TruthTable {
+        let tt = self.drop_redundant_vars();
+
+        let mut vars = tt.vars().to_vec();
+        vars.sort();
+
+        let perm: Vec<usize> = vars.iter().map(|v| tt.vars().iter().position(|w| w == v).unwrap()).collect();
+
+        TruthTable::tabulate(&vars, |x| {
+            let mut r = 0;
+            for (j, i) in perm.iter().enumerate() {
+                if x & (1 << j) != 0 {
+                    r |= 1 << i;
+                }
+            }
+            tt.eval_bits(r)
+        })
+    }
+
+    pub fn difference(&self, that: &TruthTable) -> Option<BTreeMap<Var, bool>> {
+        match bitop(self, that, &|l, r| l != r) {
+            TruthTable::AlwaysTrue => Some(BTreeMap::new()),
+            TruthTable::AlwaysFalse => None,
+            TruthTable::Explicit{vars, table} => {
+                let x = table.iter().position(|b| *b)?;
+                Some(vars.iter().enumerate().map(|(i, v)| (v.clone(), x & (1 << i) != 0)).collect())
+            },
+        }
+    }
+
+    // A lower bound on the number of terms in any sum-of-products cover, cheap compared to dnf():
+    // true rows which pairwise cannot share a term each need a term of their own.
+    pub fn estimate_terms(&self) -> usize {
+        self.estimate_terms_care(&TruthTable::always())
+    }
+
+    // As estimate_terms(), but rows outside the care set may be covered or not as convenient.
+    pub fn estimate_terms_care(&self, care: &TruthTable) -> usize {
+        let on = self & care;
+        let allowed = bitop(self, care, &|a, c| a | !c);
+
+        match (&on, &allowed) {
+            (TruthTable::Explicit{table: on, ..}, TruthTable::Explicit{table: allowed, ..}) => {
+                let cube_is_allowed = |x: usize, y: usize| {
+                    let diff = x ^ y;
+                    let base = x & !diff;
+                    let mut s = diff;
+                    loop {
+                        if !allowed[base | s] {
+                            return false;
+                        }
+                        if s == 0 {
+                            return true;
+                        }
+                        s = (s - 1) & diff;
+                    }
+                };
+
+                let mut chosen: Vec<usize> = Vec::new();
+                for (x, r) in on.iter().enumerate() {
+                    if *r && chosen.iter().all(|y| !cube_is_allowed(x, *y)) {
+                        chosen.push(x);
+                    }
+                }
+                chosen.len()
+            },
+            _ => if on == TruthTable::never() { 0 } else { 1 },
+        }
+    }
+
+    pub fn pos(&self) -> ProductOfSums { ProductOfSums((!self).dnf()) }
+
     pub fn dnf(&self) -> Product {
+        self.dnf_care(&TruthTable::always())
+    }
+
+    // Rows outside the care set are don't-cares: the cover may include them if that saves terms.
+    pub fn dnf_care(&self, care: &TruthTable) -> Product {
         match self {
-            TruthTable::AlwaysTrue => Product { invert: true, terms: Vec::new() },
+            TruthTable::AlwaysTrue => Product { invert: true, vars: Vec::new(), terms: Vec::new() },
 
-            TruthTable::AlwaysFalse => Product { invert: false, terms: Vec::new() },
+            TruthTable::AlwaysFalse => Product { invert: false, vars: Vec::new(), terms: Vec::new() },
 
             TruthTable::Explicit{vars, ..} => {
                 let mut terms = Vec::new();
                 let mut product = TruthTable::never();
 
-                while self != &product {
+                let on = self & care;
+                let off = !self & care;
+
+                while on != (&product & care) {
                     let mut bestterm = vars.iter().map(|_| Factor::DontCare).collect();
                     let mut besttable = TruthTable::never();
                     let mut bestscore = 0;
@@ -81,11 +375,11 @@ impl TruthTable {
                             }
                         }
 
-                        if (&term_table & !self) != TruthTable::never() {
+                        if (&term_table & &off) != TruthTable::never() {
                             continue;
                         }
 
-                        let score = (&product | &term_table).true_rows();
+                        let score = ((&product | &term_table) & care).true_rows();
 
                         if score > bestscore {
                             bestterm = term;
@@ -98,12 +392,109 @@ impl TruthTable {
                     product |= besttable;
                 }
 
-                Product { invert: false, terms: terms }
+                Product { invert: false, vars: vars.clone(), terms }
             },
         }
     }
 }
 
+// Bit i of the row is the value of the i-th variable of the product.
+pub(crate) fn covers(term: &[Factor], row: u64) -> bool {
+    term.iter().enumerate().all(|(i, f)| match f {
+        Factor::DontCare => true,
+        Factor::IsFalse => row & (1 << i) == 0,
+        Factor::IsTrue => row & (1 << i) != 0,
+    })
+}
+
+impl Product {
+
+    pub fn to_truthtable(&self) -> TruthTable {
+        let tt = TruthTable::tabulate(&self.vars, |row| self.terms.iter().any(|term| covers(term, row)));
+        if self.invert { !tt } else { tt }
+    }
+
+    pub fn term_count(&self) -> usize { self.terms.len() }
+
+    pub fn literal_count(&self) -> usize {
+        self.terms.iter().map(|term| self.literals(term).count()).sum()
+    }
+
+    // Number of terms each variable appears in, either inverted or not.
+    pub fn var_usage(&self) -> BTreeMap<Var, usize> {
+        self.vars.iter().enumerate().map(|(i, v)| (v.clone(), self.terms.iter().filter(|term| term[i] != Factor::DontCare).count())).collect()
+    }
+
+    pub fn implements(&self, tt: &TruthTable) -> bool {
+        self.implements_care(tt, &TruthTable::always())
+    }
+
+    pub fn implements_care(&self, tt: &TruthTable, care: &TruthTable) -> bool {
+        ((&self.to_truthtable() ^ tt) & care) == TruthTable::never()
+    }
+
+    pub(crate) fn literals<'a>(&'a self, term: &'a [Factor]) -> impl Iterator<Item = (&'a Var, bool)> + 'a {
+        self.vars.iter().zip(term).filter_map(|(v, f)| match f {
+            Factor::DontCare => None,
+            Factor::IsFalse => Some((v, false)),
+            Factor::IsTrue => Some((v, true)),
+        })
+    }
+}
+
+// Sum-of-products form, e.g. "/bit3 & bit2 | rin", wrapped in "/(...)" for negative polarity.
+impl fmt::Display for Product {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.invert {
+            write!(f, "/(")?;
+        }
+        if self.terms.is_empty() {
+            write!(f, "0")?;
+        }
+        for (i, term) in self.terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            let literals: Vec<String> = self.literals(term).map(|(v, b)| format!("{}{}", if b { "" } else { "/" }, v)).collect();
+            if literals.is_empty() {
+                write!(f, "1")?;
+            } else {
+                write!(f, "{}", literals.join(" & "))?;
+            }
+        }
+        if self.invert {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ProductOfSums {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let off = &self.0;
+        if off.invert {
+            return write!(f, "0");
+        }
+        if off.terms.is_empty() {
+            write!(f, "1")?;
+        }
+        for (i, term) in off.terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, " & ")?;
+            }
+            let literals: Vec<String> = off.literals(term).map(|(v, b)| format!("{}{}", if b { "/" } else { "" }, v)).collect();
+            if literals.is_empty() {
+                write!(f, "0")?;
+            } else if literals.len() > 1 && off.terms.len() > 1 {
+                write!(f, "({})", literals.join(" | "))?;
+            } else {
+                write!(f, "{}", literals.join(" | "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl From<&str> for TruthTable {
     fn from(name: &str) -> TruthTable {
         let v = Var::from(name);
@@ -123,13 +514,13 @@ impl fmt::Display for TruthTable {
                 for v in vars {
                     write!(f, " {}", v)?;
                 }
-                write!(f, " |\n")?;
+                writeln!(f, " |")?;
                 for v in vars {
                     write!(f, "-{:->width$}", "", width=v.len())?;
                 }
                 write!(f, "-+---")?;
                 for (i, r) in table.iter().enumerate() {
-                    write!(f, "\n")?;
+                    writeln!(f)?;
                     for (j, v) in vars.iter().enumerate() {
                         write!(f, " {: >width$}", if i & (1 << j) != 0 { '1' } else { '0' }, width=v.len())?;
                     }
@@ -142,158 +533,63 @@ impl fmt::Display for TruthTable {
 }
 
 impl PartialEq for TruthTable {
-    fn eq(&self, that: &TruthTable) -> bool {
-        let mut vs = BTreeMap::new();
+    fn eq(&self, that: &TruthTable) -> bool { self.difference(that).is_none() }
+}
 
-        match self {
-            TruthTable::Explicit{vars, ..} => {
-                for (i, v) in vars.iter().enumerate() {
-                    vs.insert(&*v.name, (Some(i), None));
-                }
-            },
-            _ => (),
-        };
+impl Eq for TruthTable {}
 
-        match that {
-            TruthTable::Explicit{vars, ..} => {
-                for (i, v) in vars.iter().enumerate() {
-                    vs.entry(&*v.name).or_insert((None, None)).1 = Some(i);
-                }
+impl Hash for TruthTable {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.canonical() {
+            TruthTable::AlwaysTrue => true.hash(state),
+            TruthTable::AlwaysFalse => false.hash(state),
+            TruthTable::Explicit{vars, table} => {
+                vars.hash(state);
+                table.hash(state);
             },
-            _ => (),
-        };
-
-        if vs.len() == 0 {
-            match self {
-                TruthTable::AlwaysTrue => match that {
-                    TruthTable::AlwaysTrue => return true,
-                    TruthTable::AlwaysFalse => return false,
-                    TruthTable::Explicit{..} => panic!("Empty explicit truth table"),
-                },
-                TruthTable::AlwaysFalse => match that {
-                    TruthTable::AlwaysTrue => return false,
-                    TruthTable::AlwaysFalse => return true,
-                    TruthTable::Explicit{..} => panic!("Empty explicit truth table"),
-                },
-                TruthTable::Explicit{..} => panic!("Empty explicit truth table"),
-            }
-        }
-
-        for x in 0 .. (1 << vs.len()) {
-            let mut lr = 0;
-            let mut rr = 0;
-
-            for (i, (_, (li, ri))) in vs.iter().enumerate() {
-                let b = x & (1 << i) != 0;
-                if b {
-                    if let Some(li) = li {
-                        lr |= 1 << li;
-                    }
-                    if let Some(ri) = ri {
-                        rr |= 1 << ri;
-                    }
-                }
-            }
-
-            let lv = match self {
-                TruthTable::AlwaysTrue => true,
-                TruthTable::AlwaysFalse => false,
-                TruthTable::Explicit{table, ..} => table[lr],
-            };
-
-            let rv = match that {
-                TruthTable::AlwaysTrue => true,
-                TruthTable::AlwaysFalse => false,
-                TruthTable::Explicit{table, ..} => table[rr],
-            };
-
-            if lv != rv {
-                return false;
-            }
         }
-
-        true
     }
 }
 
-impl Eq for TruthTable {}
-
-fn bitop(l: &TruthTable, r: &TruthTable, f: &dyn Fn(bool, bool) -> bool) -> TruthTable {
+// Combines any number of tables row by row over the union of their variables, sorted by name.
+fn tableop(tts: &[&TruthTable], f: &dyn Fn(&[bool]) -> bool) -> TruthTable {
     let mut vs = BTreeMap::new();
 
-    match l {
-        TruthTable::Explicit{vars, ..} => {
-            for (i, v) in vars.iter().enumerate() {
-                vs.insert(&*v.name, (Some(i), None));
-            }
-        },
-        _ => (),
-    };
-
-    match r {
-        TruthTable::Explicit{vars, ..} => {
-            for (i, v) in vars.iter().enumerate() {
-                vs.entry(&*v.name).or_insert((None, None)).1 = Some(i);
-            }
-        },
-        _ => (),
-    };
-
-    if vs.len() == 0 {
-        match l {
-            TruthTable::AlwaysTrue => match r {
-                TruthTable::AlwaysTrue => if f(true, true) { return TruthTable::AlwaysTrue } else { return TruthTable::AlwaysFalse },
-                TruthTable::AlwaysFalse => if f(true, false) { return TruthTable::AlwaysTrue } else { return TruthTable::AlwaysFalse },
-                TruthTable::Explicit{..} => panic!("Empty explicit truth table"),
-            },
-            TruthTable::AlwaysFalse => match r {
-                TruthTable::AlwaysTrue => if f(false, true) { return TruthTable::AlwaysTrue } else { return TruthTable::AlwaysFalse },
-                TruthTable::AlwaysFalse => if f(false, false) { return TruthTable::AlwaysTrue } else { return TruthTable::AlwaysFalse },
-                TruthTable::Explicit{..} => panic!("Empty explicit truth table"),
-            },
-            TruthTable::Explicit{..} => panic!("Empty explicit truth table"),
+    for (k, tt) in tts.iter().enumerate() {
+        for (i, v) in tt.vars().iter().enumerate() {
+            vs.entry(&*v.name).or_insert_with(|| vec![None; tts.len()])[k] = Some(i);
         }
     }
 
-    let mut table = Vec::with_capacity(1 << vs.len());
-
-    for x in 0 .. (1 << vs.len()) {
-        let mut lr = 0;
-        let mut rr = 0;
-
-        for (i, (_, (li, ri))) in vs.iter().enumerate() {
-            let b = x & (1 << i) != 0;
-            if b {
-                if let Some(li) = li {
-                    lr |= 1 << li;
-                }
-                if let Some(ri) = ri {
-                    rr |= 1 << ri;
-                }
+    // For each table, which bit of the merged row goes to which bit of its own row.
+    let mut moves = vec![Vec::new(); tts.len()];
+    for (i, (_, is)) in vs.iter().enumerate() {
+        for (m, j) in moves.iter_mut().zip(is) {
+            if let Some(j) = j {
+                m.push((i, *j));
             }
         }
+    }
 
-        let lv = match l {
-            TruthTable::AlwaysTrue => true,
-            TruthTable::AlwaysFalse => false,
-            TruthTable::Explicit{table, ..} => table[lr],
-        };
+    let mut table = Vec::with_capacity(1 << vs.len());
+    let mut values = vec![false; tts.len()];
 
-        let rv = match r {
-            TruthTable::AlwaysTrue => true,
-            TruthTable::AlwaysFalse => false,
-            TruthTable::Explicit{table, ..} => table[rr],
-        };
+    for x in 0 .. (1usize << vs.len()) {
+        for ((v, tt), m) in values.iter_mut().zip(tts).zip(&moves) {
+            let r = m.iter().fold(0, |r, (i, j)| r | ((x >> i) & 1) << j);
+            *v = tt.eval_bits(r as u64);
+        }
 
-        table.push(f(lv, rv));
+        table.push(f(&values));
     }
 
     let vars = vs.iter().map(|x| Var::from(*x.0)).collect();
 
-    TruthTable::Explicit {
-        vars: vars,
-        table: table,
-    }
+    TruthTable::explicit(vars, table)
+}
+
+fn bitop(l: &TruthTable, r: &TruthTable, f: &dyn Fn(bool, bool) -> bool) -> TruthTable {
+    tableop(&[l, r], &|x| f(x[0], x[1]))
 }
 
 impl ops::BitAnd for &TruthTable {
@@ -362,10 +658,137 @@ impl ops::Not for TruthTable {
                     *x = !*x;
                 }
                 TruthTable::Explicit {
-                    vars: vars,
-                    table: table,
+                    vars,
+                    table,
                 }
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(names: &str) -> Vec<Var> {
+        names.chars().map(|c| Var::from(&*c.to_string())).collect()
+    }
+
+    // Every function of the given variables.
+    fn tables(vars: &[Var]) -> Vec<TruthTable> {
+        (0 .. 1u64 << (1 << vars.len())).map(|f| TruthTable::from_fn(vars, |row| f & (1 << row) != 0).unwrap()).collect()
+    }
+
+    // Every assignment to the given variables.
+    fn assignments(vars: &[Var]) -> Vec<BTreeMap<Var, bool>> {
+        (0 .. 1 << vars.len()).map(|row| vars.iter().enumerate().map(|(i, v)| (v.clone(), row & (1 << i) != 0)).collect()).collect()
+    }
+
+    #[test]
+    fn restrict_matches_eval() {
+        let abc = vars("abc");
+        for tt in tables(&abc) {
+            for v in &abc {
+                for value in [false, true] {
+                    let restricted = tt.restrict(v, value);
+                    assert!(!restricted.vars().contains(v));
+                    for mut x in assignments(&abc) {
+                        let got = restricted.eval(&x).unwrap();
+                        x.insert(v.clone(), value);
+                        assert_eq!(got, tt.eval(&x).unwrap());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compose_matches_eval() {
+        let (a, abcd) = (Var::from("a"), vars("abcd"));
+        for tt in tables(&vars("abc")) {
+            for g in tables(&vars("bd")) {
+                let composed = tt.compose(&a, &g);
+                for mut x in assignments(&abcd) {
+                    let got = composed.eval(&x).unwrap();
+                    x.insert(a.clone(), g.eval(&x).unwrap());
+                    assert_eq!(got, tt.eval(&x).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rename_matches_eval() {
+        let (abc, abcd) = (vars("abc"), vars("abcd"));
+        let map = |pairs: &[(&str, &str)]| pairs.iter().map(|(f, t)| (Var::from(*f), Var::from(*t))).collect::<BTreeMap<Var, Var>>();
+        // A swap, a rename to a fresh variable, and a rename that merges a into c.
+        for map in [map(&[("a", "b"), ("b", "a")]), map(&[("b", "d")]), map(&[("a", "c"), ("b", "d")])] {
+            for tt in tables(&abc) {
+                let renamed = tt.rename(&map);
+                for x in assignments(&abcd) {
+                    let y = abc.iter().map(|v| (v.clone(), x[map.get(v).unwrap_or(v)])).collect();
+                    assert_eq!(renamed.eval(&x).unwrap(), tt.eval(&y).unwrap());
+                }
+            }
+        }
+        assert_eq!((TruthTable::from("a") & !TruthTable::from("c")).rename(&map(&[("a", "c")])), TruthTable::never());
+    }
+
+    #[test]
+    fn tautology_cover_is_one_term() {
+        let vars = [Var::from("a"), Var::from("b")];
+        let tt = TruthTable::from_fn(&vars, |_| true).unwrap();
+        // Scoring a constant true table as no rows used to give /b | /a | 1 here.
+        let prod = tt.dnf();
+        assert_eq!(prod.term_count(), 1);
+        assert_eq!(prod.to_string(), "1");
+        assert!(prod.implements(&tt));
+
+        // A term that covers everything outside the care set is a constant true table too.
+        let care = TruthTable::from("a");
+        let prod = TruthTable::from_fn(&vars, |row| row & 1 != 0).unwrap().dnf_care(&care);
+        assert_eq!(prod.term_count(), 1);
+    }
+
+    #[test]
+    fn estimate_is_a_lower_bound() {
+        let vars: Vec<Var> = ["a", "b", "c", "d"].iter().map(|v| Var::from(*v)).collect();
+        let mut seed = 0x2545f4914f6cdd1du64;
+        let mut random = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            TruthTable::from_fn(&vars, |row| seed & (1 << row) != 0).unwrap()
+        };
+        for _ in 0 .. 100 {
+            let (tt, care) = (random(), random());
+            assert!(tt.estimate_terms() <= tt.dnf().term_count());
+            assert!(tt.estimate_terms_care(&care) <= tt.dnf_care(&care).term_count());
+        }
+    }
+
+    #[test]
+    fn equal_tables_hash_equal() {
+        use std::collections::hash_map::DefaultHasher;
+        let hash = |tt: &TruthTable| {
+            let mut h = DefaultHasher::new();
+            tt.hash(&mut h);
+            h.finish()
+        };
+
+        let (abc, cadb) = (vars("abc"), vars("cadb"));
+        for tt in tables(&abc) {
+            // The same function over reordered variables plus a redundant d.
+            let other = TruthTable::from_fn(&cadb, |row| {
+                let x = cadb.iter().enumerate().map(|(i, v)| (v.clone(), row & (1 << i) != 0)).collect();
+                tt.eval(&x).unwrap()
+            }).unwrap();
+            assert_eq!(tt, other);
+            assert_eq!(hash(&tt), hash(&other));
+        }
+
+        let a = TruthTable::from("a");
+        assert_eq!(hash(&(&a | &!&a)), hash(&TruthTable::always()));
+        assert_eq!(hash(&(&a & &!&a)), hash(&TruthTable::never()));
+    }
+}
@@ -1,4 +1,3 @@
-use internship;
 use std::fmt;
 
 #[derive(Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -8,6 +7,7 @@ pub struct Var {
 
 impl Var {
     pub fn len(&self) -> usize { self.name.len() }
+    pub fn is_empty(&self) -> bool { self.name.is_empty() }
 }
 
 impl Clone for Var {